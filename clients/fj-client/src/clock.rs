@@ -15,13 +15,14 @@
 
 use chrono::{DateTime, Utc};
 
+#[derive(Debug, Clone)]
 pub struct Clock {
     pub start: DateTime<Utc>,
     pub finish: DateTime<Utc>,
     // pub hold: String,
 }
 
-#[derive(PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ClockState {
     Before,
     During,
@@ -0,0 +1,102 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::time::{Duration, Instant};
+
+/// Delay before the first reconnection attempt. Doubles with each failure.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// Consecutive failed attempts before we stop calling it "reconnecting".
+const OFFLINE_AFTER_ATTEMPTS: u32 = 3;
+
+/// State of the WebSocket carrying the subscriptions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConnectionStatus {
+    #[default]
+    Connecting,
+    Connected,
+    Reconnecting {
+        attempt: u32,
+    },
+    Offline {
+        retry_at: Instant,
+    },
+}
+
+impl ConnectionStatus {
+    /// Status to show while waiting `delay` before attempt number `attempt`.
+    pub fn retrying(attempt: u32, delay: Duration) -> Self {
+        if attempt > OFFLINE_AFTER_ATTEMPTS {
+            ConnectionStatus::Offline {
+                retry_at: Instant::now() + delay,
+            }
+        } else {
+            ConnectionStatus::Reconnecting { attempt }
+        }
+    }
+}
+
+/// Exponential backoff between reconnection attempts.
+#[derive(Debug, Default)]
+pub struct Backoff {
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of failed attempts since the last reset.
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+
+    /// Records a failed attempt and returns how long to wait before the next one.
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = INITIAL_BACKOFF
+            .checked_mul(2u32.saturating_pow(self.attempt))
+            .unwrap_or(MAX_BACKOFF)
+            .min(MAX_BACKOFF);
+        self.attempt += 1;
+        delay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let mut backoff = Backoff::new();
+
+        assert_eq!(backoff.next_delay(), Duration::from_millis(500));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(2));
+
+        for _ in 0..100 {
+            assert!(backoff.next_delay() <= MAX_BACKOFF);
+        }
+        assert_eq!(backoff.attempt(), 103);
+
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), INITIAL_BACKOFF);
+    }
+}
@@ -0,0 +1,208 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::fmt;
+
+use reqwest::StatusCode;
+
+/// Message the server gives for operations guarded by the competition clock.
+const NOT_RUNNING_MESSAGE: &str = "Competition is not running";
+
+/// Everything that can go wrong talking to a FuzzJudge server.
+#[derive(Debug)]
+pub enum Error {
+    /// The server URL couldn't be parsed or isn't `http(s)://`.
+    InvalidUrl(String),
+    /// The request couldn't be sent or its response couldn't be read.
+    Network(reqwest::Error),
+    /// The server responded with an unexpected HTTP status and no GraphQL errors.
+    Http(StatusCode),
+    /// The response wasn't a GraphQL response we understand.
+    InvalidResponse(String),
+    /// The credentials were refused, or the session has expired and we need to log in again.
+    Auth(String),
+    /// The operation is only available while the competition is running, and it isn't.
+    NotRunning,
+    /// The server answered with GraphQL errors.
+    GraphQL(Vec<GraphQLError>),
+    /// The WebSocket carrying the subscriptions couldn't be opened or failed.
+    WebSocket(String),
+    /// The server answered with neither data nor errors.
+    NoData,
+    /// The logged in user isn't in a team, so can't have submissions.
+    NoTeam,
+    /// The judge didn't accept a submission. `errors` is the judge's error output.
+    Rejected { message: String, errors: String },
+}
+
+/// One entry of the `errors` array in a GraphQL response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphQLError {
+    pub message: String,
+    /// Machine readable `extensions.code`, if the server set one.
+    pub code: Option<String>,
+    /// Where in the response the error happened, e.g. `problems/0/instructions`.
+    pub path: Option<String>,
+}
+
+impl Error {
+    /// Works out what a GraphQL response with `errors` means, given its HTTP `status`.
+    pub(crate) fn from_response(status: StatusCode, errors: Vec<graphql_client::Error>) -> Self {
+        let errors: Vec<GraphQLError> = errors.into_iter().map(GraphQLError::from).collect();
+
+        if errors
+            .iter()
+            .any(|e| e.message.starts_with(NOT_RUNNING_MESSAGE))
+        {
+            return Error::NotRunning;
+        }
+
+        let unauthenticated = errors
+            .iter()
+            .find(|e| e.code.as_deref() == Some("UNAUTHENTICATED"));
+        if let Some(e) = unauthenticated {
+            return Error::Auth(e.message.clone());
+        }
+        if status == StatusCode::UNAUTHORIZED {
+            let message = errors.first().map(|e| e.message.clone());
+            return Error::Auth(message.unwrap_or_else(|| "Unauthorized".to_string()));
+        }
+
+        Error::GraphQL(errors)
+    }
+}
+
+impl From<graphql_client::Error> for GraphQLError {
+    fn from(e: graphql_client::Error) -> Self {
+        let code = e
+            .extensions
+            .as_ref()
+            .and_then(|extensions| extensions.get("code"))
+            .and_then(|code| code.as_str())
+            .map(str::to_string);
+
+        let path = e.path.map(|fragments| {
+            fragments
+                .iter()
+                .map(|f| f.to_string())
+                .collect::<Vec<String>>()
+                .join("/")
+        });
+
+        Self {
+            message: e.message,
+            code,
+            path,
+        }
+    }
+}
+
+impl fmt::Display for GraphQLError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(path) = &self.path {
+            write!(f, " (at {})", path)?;
+        }
+        if let Some(code) = &self.code {
+            write!(f, " [{}]", code)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidUrl(e) => write!(f, "Invalid server URL: {}", e),
+            Error::Network(e) => write!(f, "Couldn't reach the server: {}", e),
+            Error::Http(status) => write!(f, "Server responded with {}", status),
+            Error::InvalidResponse(e) => write!(f, "Invalid response from server: {}", e),
+            Error::Auth(message) => write!(f, "Authentication failed: {}", message),
+            Error::NotRunning => write!(f, "The competition isn't running."),
+            Error::GraphQL(errors) => {
+                let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "GraphQL errors: {}", messages.join("; "))
+            }
+            Error::WebSocket(e) => write!(f, "WebSocket error: {}", e),
+            Error::NoData => write!(f, "No data in response"),
+            Error::NoTeam => write!(f, "You are not in a team, so have no submissions."),
+            Error::Rejected { message, errors } => {
+                write!(f, "{}", message)?;
+                if !errors.is_empty() {
+                    write!(f, "\n{}", errors)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Network(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Network(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graphql_error(json: &str) -> graphql_client::Error {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_from_response() {
+        let not_running = graphql_error(
+            r#"{"message": "Competition is not running. Please wait until it starts.",
+                "path": ["problems", 0, "instructions"]}"#,
+        );
+        assert!(matches!(
+            Error::from_response(StatusCode::FORBIDDEN, vec![not_running]),
+            Error::NotRunning
+        ));
+
+        let unauthorized = graphql_error(r#"{"message": "Unauthorized"}"#);
+        assert!(matches!(
+            Error::from_response(StatusCode::UNAUTHORIZED, vec![unauthorized]),
+            Error::Auth(m) if m == "Unauthorized"
+        ));
+
+        let other = graphql_error(
+            r#"{"message": "Team not found", "path": ["team"],
+                "extensions": {"code": "NOT_FOUND"}}"#,
+        );
+        match Error::from_response(StatusCode::OK, vec![other]) {
+            Error::GraphQL(errors) => assert_eq!(
+                errors,
+                [GraphQLError {
+                    message: "Team not found".to_string(),
+                    code: Some("NOT_FOUND".to_string()),
+                    path: Some("team".to_string()),
+                }]
+            ),
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+}
@@ -0,0 +1,60 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Async client for the FuzzJudge GraphQL API.
+//!
+//! Log in with a [`Session`], then use it to fetch problems, get fuzz inputs and submit
+//! solutions for judging. [`subscribe`] keeps the clock, scoreboard and announcements up to
+//! date over a WebSocket, reconnecting whenever the connection drops.
+//!
+//! ```no_run
+//! use fj_client::{Credentials, Session};
+//!
+//! # async fn example() -> Result<(), fj_client::Error> {
+//! let session = Session::new("http://localhost:1989", Credentials::new("team", "hunter2")).await?;
+//!
+//! for problem in session.fetch_all_problems().await? {
+//!     let input = session.fuzz(&problem.slug).await?;
+//!     let answer = input.lines().count().to_string();
+//!     println!("{}", session.judge(&problem.slug, &answer, "# counted the lines").await?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+mod auth;
+mod broadcast;
+mod clock;
+mod competition;
+mod connection;
+mod error;
+mod problem;
+pub mod queries;
+mod scoreboard;
+mod session;
+mod submission;
+mod subscription;
+
+pub use auth::Credentials;
+pub use broadcast::Broadcast;
+pub use clock::{Clock, ClockState};
+pub use competition::Competition;
+pub use connection::{Backoff, ConnectionStatus};
+pub use error::{Error, GraphQLError};
+pub use problem::Problem;
+pub use scoreboard::{ProblemScore, ScoreboardRow};
+pub use session::{Session, Team};
+pub use submission::Submission;
+pub use subscription::{subscribe, Update};
@@ -13,12 +13,12 @@
  * with this program. If not, see <https://www.gnu.org/licenses/>.
  */
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Problem {
     pub slug: String,
     pub title: String,
     pub icon: String,
     pub difficulty: i64,
     pub points: i64,
-    pub instructions: Option<String>
+    pub instructions: Option<String>,
 }
@@ -0,0 +1,91 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! GraphQL operations generated from the server's schema. [`Session`](crate::Session) wraps the
+//! common ones; use these with [`Session::query`](crate::Session::query) for anything else.
+
+use graphql_client::GraphQLQuery;
+
+type DateTime = chrono::DateTime<chrono::Utc>;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "../../server/src/schema/schema.generated.graphqls",
+    query_path = "src/queries/ProblemQuery.gql"
+)]
+pub struct ProblemQuery;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "../../server/src/schema/schema.generated.graphqls",
+    query_path = "src/queries/ProblemsQuery.gql"
+)]
+pub struct ProblemsQuery;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "../../server/src/schema/schema.generated.graphqls",
+    query_path = "src/queries/ClockSubscription.gql"
+)]
+pub struct ClockSubscription;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "../../server/src/schema/schema.generated.graphqls",
+    query_path = "src/queries/ScoreboardSubscription.gql"
+)]
+pub struct ScoreboardSubscription;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "../../server/src/schema/schema.generated.graphqls",
+    query_path = "src/queries/BroadcastSubscription.gql"
+)]
+pub struct BroadcastSubscription;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "../../server/src/schema/schema.generated.graphqls",
+    query_path = "src/queries/CurrentUserQuery.gql"
+)]
+pub struct CurrentUserQuery;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "../../server/src/schema/schema.generated.graphqls",
+    query_path = "src/queries/JudgeProblemMutation.gql"
+)]
+pub struct JudgeProblemMutation;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "../../server/src/schema/schema.generated.graphqls",
+    query_path = "src/queries/Login.gql"
+)]
+pub struct Login;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "../../server/src/schema/schema.generated.graphqls",
+    query_path = "src/queries/SubmissionsQuery.gql"
+)]
+pub struct SubmissionsQuery;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "../../server/src/schema/schema.generated.graphqls",
+    query_path = "src/queries/CompetitionQuery.gql"
+)]
+pub struct CompetitionQuery;
@@ -0,0 +1,37 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+#[derive(Debug, Default, Clone)]
+pub struct ScoreboardRow {
+    pub rank: i64,
+    pub team_id: String,
+    pub team_name: String,
+    pub points: i64,
+    pub penalty: f64,
+    pub problems: Vec<ProblemScore>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ProblemScore {
+    pub slug: String,
+    pub solved: bool,
+    pub tries: i64,
+}
+
+impl ScoreboardRow {
+    pub fn problem(&self, slug: &str) -> Option<&ProblemScore> {
+        self.problems.iter().find(|p| p.slug == slug)
+    }
+}
@@ -0,0 +1,316 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::sync::Arc;
+
+use graphql_client::{GraphQLQuery, Response};
+use reqwest::{cookie::CookieStore, header::HeaderValue, StatusCode};
+use tokio_tungstenite::tungstenite::{client::IntoClientRequest, handshake::client::Request};
+use url::Url;
+
+use crate::{
+    auth::Credentials,
+    competition::Competition,
+    error::Error,
+    problem::Problem,
+    queries::{
+        competition_query, judge_problem_mutation, login, problem_query, problems_query,
+        submissions_query, CompetitionQuery, JudgeProblemMutation, Login, ProblemQuery,
+        ProblemsQuery, SubmissionsQuery,
+    },
+    submission::Submission,
+};
+
+/// A logged in connection to a FuzzJudge server.
+///
+/// Requests only need `&self`, so a session can be shared between tasks with an [`Arc`].
+pub struct Session {
+    pub creds: Credentials,
+    pub server: Url,
+    pub client: reqwest::Client,
+    /// Shared with `client` so the session cookie can also be sent with the WebSocket handshake.
+    cookies: Arc<reqwest::cookie::Jar>,
+    /// Team of the logged in user, if they are in one.
+    pub team: Option<Team>,
+}
+
+/// The team a user competes in.
+#[derive(Debug, Clone)]
+pub struct Team {
+    pub id: String,
+    pub name: String,
+}
+
+impl Session {
+    /// Connects to `server` (an `http://` or `https://` URL) and logs in.
+    pub async fn new(server: &str, creds: Credentials) -> Result<Self, Error> {
+        let server = Url::parse(server).map_err(|e| Error::InvalidUrl(e.to_string()))?;
+        if server.scheme() != "http" && server.scheme() != "https" {
+            return Err(Error::InvalidUrl(
+                "Server URL must start with http:// or https://".to_string(),
+            ));
+        }
+
+        let cookies = Arc::new(reqwest::cookie::Jar::default());
+
+        let client = reqwest::Client::builder()
+            .cookie_provider(cookies.clone()) // Keep cookies to handle sessions
+            .user_agent(concat!(
+                env!("CARGO_PKG_NAME"),
+                "/",
+                env!("CARGO_PKG_VERSION")
+            ))
+            .build()?;
+
+        let mut session = Self {
+            server,
+            creds,
+            client,
+            cookies,
+            team: None,
+        };
+
+        session.team = session.login().await?;
+
+        Ok(session)
+    }
+
+    /// A session for `server` that hasn't logged in. Requests needing authentication will
+    /// fail until [`Session::login`] succeeds.
+    pub fn new_unauthenticated(server: Url) -> Self {
+        Self {
+            server,
+            creds: Credentials::default(),
+            client: reqwest::Client::new(),
+            cookies: Arc::new(reqwest::cookie::Jar::default()),
+            team: None,
+        }
+    }
+
+    /// For tests. An unauthenticated session for `http://localhost`.
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn new_no_connection() -> Self {
+        Self::new_unauthenticated(Url::parse("http://localhost").unwrap())
+    }
+
+    /// Logs in with the session's credentials, refreshing the session cookie. Returns the user's
+    /// team.
+    pub async fn login(&self) -> Result<Option<Team>, Error> {
+        let result = self
+            .query::<Login>(login::Variables {
+                username: self.creds.username.clone(),
+                password: self.creds.password.clone(),
+            })
+            .await;
+
+        match result {
+            Ok(data) => Ok(data.login.team.map(|team| Team {
+                id: team.id,
+                name: team.name,
+            })),
+            // The only reason login gives an error is bad credentials.
+            Err(Error::GraphQL(errors)) => {
+                let messages: Vec<String> = errors.into_iter().map(|e| e.message).collect();
+                Err(Error::Auth(messages.join("; ")))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Sends any GraphQL operation to the server.
+    pub async fn query<Q: GraphQLQuery>(
+        &self,
+        variables: Q::Variables,
+    ) -> Result<Q::ResponseData, Error> {
+        let response = self
+            .client
+            .post(self.graphql_url())
+            .json(&Q::build_query(variables))
+            .send()
+            .await?;
+
+        let status = response.status();
+        let body = response.bytes().await?;
+
+        let response: Response<Q::ResponseData> = match serde_json::from_slice(&body) {
+            Ok(response) => response,
+            Err(_) if status == StatusCode::UNAUTHORIZED => {
+                return Err(Error::Auth("Unauthorized".to_string()));
+            }
+            Err(_) if !status.is_success() => return Err(Error::Http(status)),
+            Err(e) => return Err(Error::InvalidResponse(e.to_string())),
+        };
+
+        if let Some(errors) = response.errors {
+            return Err(Error::from_response(status, errors));
+        }
+
+        match response.data {
+            Some(data) => Ok(data),
+            None if !status.is_success() => Err(Error::Http(status)),
+            None => Err(Error::NoData),
+        }
+    }
+
+    /// `http://` or `https://` URL of the GraphQL endpoint.
+    pub fn graphql_url(&self) -> Url {
+        self.server.join("/graphql").expect("Invalid GraphQL URL")
+    }
+
+    /// `ws://` or `wss://` URL of the GraphQL endpoint.
+    pub fn web_socket_url(&self) -> Url {
+        let mut addr = self.graphql_url();
+        if addr.scheme() == "http" {
+            addr.set_scheme("ws").unwrap();
+        } else if addr.scheme() == "https" {
+            addr.set_scheme("wss").unwrap();
+        }
+        addr
+    }
+
+    /// Handshake request for the GraphQL WebSocket, authenticated with the session cookie.
+    pub(crate) fn web_socket_request(&self) -> Result<Request, Error> {
+        let mut request = self
+            .web_socket_url()
+            .as_str()
+            .into_client_request()
+            .map_err(|e| Error::WebSocket(e.to_string()))?;
+
+        request.headers_mut().insert(
+            "Sec-WebSocket-Protocol",
+            HeaderValue::from_static("graphql-transport-ws"),
+        );
+
+        if let Some(cookies) = self.cookies.cookies(&self.server) {
+            request.headers_mut().insert("Cookie", cookies);
+        }
+
+        Ok(request)
+    }
+
+    /// Fetches our team's input for a problem.
+    pub async fn fuzz(&self, slug: &str) -> Result<String, Error> {
+        let data = self
+            .query::<ProblemQuery>(problem_query::Variables {
+                slug: slug.to_string(),
+            })
+            .await?;
+
+        Ok(data.problem.fuzz)
+    }
+
+    /// Submits `output` for a problem along with the `code` that produced it. Returns the
+    /// judge's message if it was accepted, or [`Error::Rejected`] if it wasn't.
+    pub async fn judge(&self, slug: &str, output: &str, code: &str) -> Result<String, Error> {
+        let data = self
+            .query::<JudgeProblemMutation>(judge_problem_mutation::Variables {
+                slug: slug.to_string(),
+                output: output.to_string(),
+                code: code.to_string(),
+            })
+            .await?;
+
+        match data.judge {
+            judge_problem_mutation::JudgeProblemMutationJudge::JudgeErrorOutput(e) => {
+                Err(Error::Rejected {
+                    message: e.message,
+                    errors: e.errors,
+                })
+            }
+            judge_problem_mutation::JudgeProblemMutationJudge::JudgeSuccessOutput(s) => {
+                Ok(s.message)
+            }
+        }
+    }
+
+    pub async fn fetch_all_problems(&self) -> Result<Vec<Problem>, Error> {
+        let data = self
+            .query::<ProblemsQuery>(problems_query::Variables {})
+            .await?;
+
+        let problems: Vec<Problem> = data
+            .problems
+            .into_iter()
+            .map(|p| Problem {
+                slug: p.slug,
+                title: p.name,
+                icon: p.icon,
+                difficulty: p.difficulty,
+                points: p.points,
+                instructions: Some(p.instructions),
+            })
+            .collect();
+
+        Ok(problems)
+    }
+
+    pub async fn fetch_competition(&self) -> Result<Competition, Error> {
+        let data = self
+            .query::<CompetitionQuery>(competition_query::Variables {})
+            .await?;
+
+        Ok(Competition {
+            name: data.competition.name,
+            instructions: data.competition.instructions,
+        })
+    }
+
+    /// Fetches our team's submissions for a problem, newest first.
+    pub async fn fetch_submissions(&self, slug: &str) -> Result<Vec<Submission>, Error> {
+        let team_id = self.team.as_ref().ok_or(Error::NoTeam)?.id.clone();
+
+        let data = self
+            .query::<SubmissionsQuery>(submissions_query::Variables {
+                problem_slug: Some(slug.to_string()),
+                team_id: Some(team_id),
+            })
+            .await?;
+
+        let mut submissions: Vec<Submission> = data
+            .submissions
+            .into_iter()
+            .map(|s| Submission {
+                id: s.id,
+                ok: s.ok,
+                out: s.out,
+                code: s.code,
+                time: s.time,
+                vler: s.vler,
+                vlms: s.vlms,
+            })
+            .collect();
+
+        submissions.sort_by_key(|s| std::cmp::Reverse(s.time));
+
+        Ok(submissions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_web_socket_url() {
+        let session = Session::new_unauthenticated(Url::parse("https://fj.example/comp").unwrap());
+        assert_eq!(
+            session.web_socket_url().as_str(),
+            "wss://fj.example/graphql"
+        );
+
+        let session = Session::new_no_connection();
+        assert_eq!(session.web_socket_url().as_str(), "ws://localhost/graphql");
+    }
+}
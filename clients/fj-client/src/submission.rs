@@ -0,0 +1,40 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Clone)]
+pub struct Submission {
+    pub id: String,
+    /// `None` until the submission has been judged.
+    pub ok: Option<bool>,
+    pub out: Option<String>,
+    pub code: Option<String>,
+    pub time: DateTime<Utc>,
+    /// Error output from the judge.
+    pub vler: Option<String>,
+    /// Time the judge took, in milliseconds.
+    pub vlms: Option<f64>,
+}
+
+impl Submission {
+    pub fn verdict(&self) -> &'static str {
+        match self.ok {
+            Some(true) => "Accepted",
+            Some(false) => "Rejected",
+            None => "Pending",
+        }
+    }
+}
@@ -0,0 +1,226 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{future::IntoFuture, time::Duration};
+
+use futures_util::StreamExt;
+use graphql_ws_client::{graphql::StreamingOperation, Client, Subscription};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    broadcast::Broadcast,
+    clock::Clock,
+    connection::{Backoff, ConnectionStatus},
+    error::Error,
+    queries::{
+        broadcast_subscription, clock_subscription, scoreboard_subscription, BroadcastSubscription,
+        ClockSubscription, ScoreboardSubscription,
+    },
+    scoreboard::{ProblemScore, ScoreboardRow},
+    session::Session,
+};
+
+/// How often to ping the server over the WebSocket, so a dead connection is noticed.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Something the server pushed to us, or a change in the connection.
+#[derive(Debug)]
+pub enum Update {
+    Connection(ConnectionStatus),
+    /// Connecting failed after the connection was lost (or before it was ever made). Only sent
+    /// for the first attempt; retries carry on regardless.
+    ConnectionFailed(Error),
+    Clock(Clock),
+    /// The whole scoreboard, ordered by rank.
+    Scoreboard(Vec<ScoreboardRow>),
+    /// A new announcement. Announcements made before subscribing aren't sent.
+    Broadcast(Broadcast),
+}
+
+/// Keeps the clock, scoreboard and broadcast subscriptions running, sending everything they
+/// receive to `updates`. Reconnects with exponential backoff whenever the connection drops,
+/// logging in again first in case the session expired. Returns once `updates` is closed, as
+/// soon as there is something it would have sent.
+pub async fn subscribe(session: &Session, updates: UnboundedSender<Update>) {
+    let mut backoff = Backoff::new();
+
+    while !updates.is_closed() {
+        let mut connected = false;
+        let result = run_subscriptions(session, &updates, &mut connected).await;
+        if updates.is_closed() {
+            return;
+        }
+
+        if connected {
+            backoff.reset();
+        } else if let (Err(e), 0) = (result, backoff.attempt()) {
+            let _ = updates.send(Update::ConnectionFailed(e));
+        }
+
+        let delay = backoff.next_delay();
+        let status = ConnectionStatus::retrying(backoff.attempt(), delay);
+        if updates.send(Update::Connection(status)).is_err() {
+            return;
+        }
+        tokio::time::sleep(delay).await;
+
+        // If this fails so will the connection, and we'll try again.
+        let _ = session.login().await;
+    }
+}
+
+/// Connects and forwards every subscription to `updates` until the connection is lost.
+async fn run_subscriptions(
+    session: &Session,
+    updates: &UnboundedSender<Update>,
+    connected: &mut bool,
+) -> Result<(), Error> {
+    let request = session.web_socket_request()?;
+
+    let (connection, _) = async_tungstenite::tokio::connect_async(request)
+        .await
+        .map_err(|e| Error::WebSocket(e.to_string()))?;
+
+    let (client, actor) = Client::build(connection)
+        .keep_alive_interval(KEEP_ALIVE_INTERVAL)
+        .await
+        .map_err(|e| Error::WebSocket(e.to_string()))?;
+
+    tokio::select! {
+        _ = actor.into_future() => Ok(()),
+        result = watch_subscriptions(client, updates, connected) => result,
+    }
+}
+
+async fn watch_subscriptions(
+    client: Client,
+    updates: &UnboundedSender<Update>,
+    connected: &mut bool,
+) -> Result<(), Error> {
+    let clock_subscription = client
+        .subscribe(StreamingOperation::<ClockSubscription>::new(
+            clock_subscription::Variables,
+        ))
+        .await
+        .map_err(|e| Error::WebSocket(e.to_string()))?;
+
+    let scoreboard_subscription = client
+        .subscribe(StreamingOperation::<ScoreboardSubscription>::new(
+            scoreboard_subscription::Variables,
+        ))
+        .await
+        .map_err(|e| Error::WebSocket(e.to_string()))?;
+
+    let broadcast_subscription = client
+        .subscribe(StreamingOperation::<BroadcastSubscription>::new(
+            broadcast_subscription::Variables,
+        ))
+        .await
+        .map_err(|e| Error::WebSocket(e.to_string()))?;
+
+    *connected = true;
+    if updates
+        .send(Update::Connection(ConnectionStatus::Connected))
+        .is_err()
+    {
+        return Ok(());
+    }
+
+    // Any subscription ending means the connection has gone, or that no one is listening.
+    tokio::select! {
+        _ = watch_clock(clock_subscription, updates) => {}
+        _ = watch_scoreboard(scoreboard_subscription, updates) => {}
+        _ = watch_broadcasts(broadcast_subscription, updates) => {}
+    }
+
+    Ok(())
+}
+
+async fn watch_clock(
+    mut subscription: Subscription<StreamingOperation<ClockSubscription>>,
+    updates: &UnboundedSender<Update>,
+) {
+    while let Some(item) = subscription.next().await {
+        if let Ok(message) = item {
+            if let Some(clock_state) = message.data {
+                let clock = Clock {
+                    start: clock_state.clock.start,
+                    finish: clock_state.clock.finish,
+                };
+                if updates.send(Update::Clock(clock)).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+async fn watch_scoreboard(
+    mut subscription: Subscription<StreamingOperation<ScoreboardSubscription>>,
+    updates: &UnboundedSender<Update>,
+) {
+    while let Some(item) = subscription.next().await {
+        if let Ok(message) = item {
+            if let Some(data) = message.data {
+                let mut rows: Vec<ScoreboardRow> = data
+                    .scoreboard
+                    .into_iter()
+                    .map(|row| ScoreboardRow {
+                        rank: row.rank,
+                        team_id: row.team_id,
+                        team_name: row.team.name,
+                        points: row.points,
+                        penalty: row.penalty,
+                        problems: row
+                            .problems
+                            .into_iter()
+                            .map(|p| ProblemScore {
+                                slug: p.slug,
+                                solved: p.solved,
+                                tries: p.tries,
+                            })
+                            .collect(),
+                    })
+                    .collect();
+
+                rows.sort_by_key(|row| row.rank);
+
+                if updates.send(Update::Scoreboard(rows)).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+async fn watch_broadcasts(
+    mut subscription: Subscription<StreamingOperation<BroadcastSubscription>>,
+    updates: &UnboundedSender<Update>,
+) {
+    while let Some(item) = subscription.next().await {
+        if let Ok(message) = item {
+            if let Some(data) = message.data {
+                let broadcast = Broadcast {
+                    id: data.broadcasts.id,
+                    title: data.broadcasts.title,
+                    content: data.broadcasts.content,
+                };
+                if updates.send(Update::Broadcast(broadcast)).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
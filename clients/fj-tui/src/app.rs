@@ -0,0 +1,349 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{io, sync::Arc, time::Duration};
+
+use ratatui::{backend::Backend, crossterm, Terminal};
+use tokio::sync::{mpsc, oneshot};
+
+use fj_client::{
+    Broadcast, Clock, Competition, ConnectionStatus, Error, Problem, ScoreboardRow, Session,
+    Submission, Update,
+};
+
+use crate::{console::Confirm, shell, state::AppState, ui, watch::WatchRun};
+
+/// Everything that can change the app state. Events are sent to the event loop, which is the
+/// only thing that owns and mutates the state.
+pub enum Event {
+    /// Input from the terminal.
+    Terminal(crossterm::event::Event),
+    /// Sent every second to keep the countdown and toasts up to date.
+    Tick,
+    Clock(Clock),
+    Scoreboard(Vec<ScoreboardRow>),
+    Broadcast(Broadcast),
+    Connection(ConnectionStatus),
+    Problems(Result<Vec<Problem>, Error>),
+    Competition(Competition),
+    Submissions {
+        slug: String,
+        result: Result<Vec<Submission>, String>,
+    },
+    /// A solution to the problem with this slug was judged, so its submission history is out of
+    /// date.
+    Judged(String),
+    /// Output of a command.
+    Print(String),
+    /// An error from the app or a built-in command.
+    EPrint(String),
+    /// Error output of an external command.
+    Stderr(String),
+    /// Progress and other messages that aren't part of a command's output.
+    Info(String),
+    ClearConsole,
+    /// A command is waiting for the user to answer yes or no.
+    Confirm(Confirm),
+    /// A command wants the slug of the problem selected now, rather than when it started.
+    SelectedProblem(oneshot::Sender<Option<String>>),
+    /// Latest run of a watched solution, or `None` once watching stops.
+    Watch(Option<WatchRun>),
+    /// A job's command line has finished running.
+    JobDone(usize),
+    /// `fg` moved a job to the foreground.
+    Foreground(usize),
+    /// `kill` asked for a job to be stopped.
+    Kill(usize),
+    Quit,
+}
+
+pub type EventSender = mpsc::UnboundedSender<Event>;
+
+/// Work for the event loop to start in response to an event. Results come back as events.
+#[derive(Debug, PartialEq)]
+pub enum Effect {
+    Exec {
+        job: usize,
+        command: String,
+        env: shell::Env,
+        output_mode: shell::OutputMode,
+    },
+    FetchProblems,
+    FetchCompetition,
+    RefreshSubmissions(String),
+}
+
+/// Draws, then applies events as they arrive until the app stops running.
+pub async fn run<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app_state: &mut AppState,
+    events: EventSender,
+    receiver: &mut mpsc::UnboundedReceiver<Event>,
+) -> io::Result<()> {
+    while app_state.running {
+        terminal.draw(|f| ui::draw(f, app_state))?;
+
+        let Some(event) = receiver.recv().await else {
+            break;
+        };
+
+        let mut effects = app_state.update(event);
+
+        // Catch up on anything else that's queued before drawing again.
+        while let Ok(event) = receiver.try_recv() {
+            effects.extend(app_state.update(event));
+        }
+
+        for effect in effects {
+            start(effect, app_state, &events);
+        }
+    }
+
+    Ok(())
+}
+
+/// Spawns the task for an effect.
+pub fn start(effect: Effect, app_state: &mut AppState, events: &EventSender) {
+    let session = app_state.session.clone();
+    let events = events.clone();
+
+    match effect {
+        Effect::Exec {
+            job,
+            command,
+            env,
+            output_mode,
+        } => {
+            let mut ctx = shell_context(app_state, &events);
+            ctx.jobs.retain(|j| j.id != job);
+
+            let task = tokio::spawn(async move {
+                shell::exec(&command, &ctx, output_mode, &env).await;
+                ctx.send(Event::JobDone(job));
+            });
+            app_state.jobs.set_handle(job, task.abort_handle());
+        }
+        Effect::FetchProblems => {
+            tokio::spawn(async move {
+                let result = session.fetch_all_problems().await;
+                let _ = events.send(Event::Problems(result));
+            });
+        }
+        Effect::FetchCompetition => {
+            tokio::spawn(async move {
+                let event = match session.fetch_competition().await {
+                    Ok(competition) => Event::Competition(competition),
+                    Err(e) => error_event(&e),
+                };
+                let _ = events.send(event);
+            });
+        }
+        Effect::RefreshSubmissions(slug) => {
+            tokio::spawn(async move {
+                let result = session
+                    .fetch_submissions(&slug)
+                    .await
+                    .map_err(|e| e.to_string());
+                let _ = events.send(Event::Submissions { slug, result });
+            });
+        }
+    }
+}
+
+/// What a command entered now can see of the app.
+pub fn shell_context(app_state: &AppState, events: &EventSender) -> shell::ShellContext {
+    shell::ShellContext {
+        session: app_state.session.clone(),
+        events: events.clone(),
+        selected: app_state.selected_problem().map(|p| p.slug.clone()),
+        jobs: app_state.jobs.iter().cloned().collect(),
+        history: app_state.console.command_history.entries().to_vec(),
+        variables: app_state.variables.clone(),
+        team: app_state.session.team.as_ref().map(|t| t.name.clone()),
+        definitions: app_state.definitions.clone(),
+        depth: 0,
+        directories: app_state.directories.clone(),
+        auto_submissions: app_state.auto_submissions.clone(),
+    }
+}
+
+/// How to tell the user about an error from the server. The competition not running is expected,
+/// so isn't shown as an error, and an expired session says how to log back in.
+pub fn error_event(e: &Error) -> Event {
+    match e {
+        Error::NotRunning => Event::Info(
+            "The competition isn't running right now. Check the countdown in the top bar."
+                .to_string(),
+        ),
+        Error::Auth(_) => {
+            Event::EPrint("Your session has expired. Run `login` to log in again.".to_string())
+        }
+        e => Event::EPrint(e.to_string()),
+    }
+}
+
+/// Keeps the subscriptions running for the rest of the session, forwarding what they receive to
+/// the event loop.
+pub fn spawn_subscriptions(session: Arc<Session>, events: EventSender) {
+    let (updates, mut receiver) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move { fj_client::subscribe(&session, updates).await });
+
+    tokio::spawn(async move {
+        while let Some(update) = receiver.recv().await {
+            let event = match update {
+                Update::Connection(status) => Event::Connection(status),
+                Update::ConnectionFailed(e) => {
+                    Event::EPrint(format!("Failed to connect to server: {}. Retrying...", e))
+                }
+                Update::Clock(clock) => Event::Clock(clock),
+                Update::Scoreboard(rows) => Event::Scoreboard(rows),
+                Update::Broadcast(broadcast) => Event::Broadcast(broadcast),
+            };
+
+            if events.send(event).is_err() {
+                // The app has closed.
+                break;
+            }
+        }
+    });
+}
+
+/// Forwards terminal input to the event loop. Reading blocks, so this gets its own thread.
+pub fn spawn_input_reader(events: EventSender) {
+    std::thread::spawn(move || {
+        while let Ok(event) = crossterm::event::read() {
+            if events.send(Event::Terminal(event)).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+pub fn spawn_ticker(events: EventSender) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            interval.tick().await;
+            if events.send(Event::Tick).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{
+        backend::TestBackend,
+        crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
+    };
+    use url::Url;
+
+    use crate::view::View;
+
+    use super::*;
+
+    fn key(code: KeyCode) -> Event {
+        Event::Terminal(crossterm::event::Event::Key(KeyEvent::new(
+            code,
+            KeyModifiers::NONE,
+        )))
+    }
+
+    #[tokio::test]
+    async fn test_input_during_slow_request() {
+        // A server that accepts connections but never answers them.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut sockets = vec![];
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+
+        let session =
+            Session::new_unauthenticated(Url::parse(&format!("http://{}", addr)).unwrap());
+        let mut app_state = AppState::new(session);
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+        let (events, mut receiver) = mpsc::unbounded_channel();
+
+        for c in ":fuzz a".chars() {
+            events.send(key(KeyCode::Char(c))).unwrap();
+        }
+        events.send(key(KeyCode::Enter)).unwrap();
+
+        // Keep using the app while the request hangs.
+        tokio::spawn({
+            let events = events.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                events.send(key(KeyCode::Tab)).unwrap();
+                events.send(key(KeyCode::Char('q'))).unwrap();
+            }
+        });
+
+        tokio::time::timeout(
+            Duration::from_secs(2),
+            run(&mut terminal, &mut app_state, events, &mut receiver),
+        )
+        .await
+        .expect("input was blocked by the request")
+        .unwrap();
+
+        assert_eq!(app_state.view, View::Problems);
+        assert_eq!(
+            app_state.console.messages.last().map(|m| m.text.as_str()),
+            Some("Request sent...")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ctrl_c() {
+        let mut app_state = AppState::new(Session::new_no_connection());
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+        let (events, mut receiver) = mpsc::unbounded_channel();
+        let marker = std::env::temp_dir().join(format!("fj-tui-ctrl-c-{}", std::process::id()));
+
+        let command = format!(":sh -c 'sleep 0.5 && touch {}'", marker.display());
+        for c in command.chars() {
+            events.send(key(KeyCode::Char(c))).unwrap();
+        }
+        events.send(key(KeyCode::Enter)).unwrap();
+
+        tokio::spawn({
+            let events = events.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                events
+                    .send(Event::Terminal(crossterm::event::Event::Key(
+                        KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL),
+                    )))
+                    .unwrap();
+                tokio::time::sleep(Duration::from_millis(600)).await;
+                events.send(key(KeyCode::Char('q'))).unwrap();
+            }
+        });
+
+        run(&mut terminal, &mut app_state, events, &mut receiver)
+            .await
+            .unwrap();
+
+        assert_eq!(app_state.jobs.len(), 0);
+        assert!(!marker.exists(), "the command wasn't killed");
+        assert!(app_state.console.messages.iter().any(|m| m.text == "^C"));
+    }
+}
@@ -0,0 +1,302 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::path::Path;
+
+use crate::shell;
+
+/// Ways to finish the word at the end of a command line.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Completion {
+    /// Byte offset of the word being completed.
+    pub start: usize,
+    pub candidates: Vec<String>,
+    /// Candidate filled in by pressing Tab again.
+    pub selected: Option<usize>,
+}
+
+impl Completion {
+    /// Longest start shared by every candidate.
+    pub fn common_prefix(&self) -> &str {
+        let Some(first) = self.candidates.first() else {
+            return "";
+        };
+
+        let mut len = first.len();
+        for candidate in &self.candidates[1..] {
+            len = first
+                .char_indices()
+                .zip(candidate.chars())
+                .take_while(|((_, a), b)| a == b)
+                .last()
+                .map_or(0, |((i, c), _)| i + c.len_utf8())
+                .min(len);
+        }
+        &first[..len]
+    }
+}
+
+/// What's been typed of the command the line ends in.
+struct Context<'a> {
+    /// Words before the one being completed.
+    words: Vec<&'a str>,
+    start: usize,
+    /// The word follows `>` or `<`.
+    redirect: bool,
+}
+
+fn context(line: &str) -> Context<'_> {
+    let mut words = vec![];
+    let mut start = None;
+    let mut redirect = false;
+    let mut quote = None;
+    let mut escaped = false;
+
+    for (i, c) in line.char_indices() {
+        if std::mem::take(&mut escaped) {
+            continue;
+        }
+
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None | Some('"'), '\\') => {
+                escaped = true;
+                start.get_or_insert(i);
+            }
+            (Some(_), _) => {}
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                start.get_or_insert(i);
+            }
+            (None, ';' | '|' | '&' | '(' | ')') => {
+                start = None;
+                words.clear();
+                redirect = false;
+            }
+            (None, '<' | '>') => {
+                if let Some(s) = start.take() {
+                    words.push(&line[s..i]);
+                }
+                redirect = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if let Some(s) = start.take() {
+                    // A redirect's target isn't one of the command's words.
+                    if redirect {
+                        redirect = false;
+                    } else {
+                        words.push(&line[s..i]);
+                    }
+                }
+            }
+            (None, _) => {
+                start.get_or_insert(i);
+            }
+        }
+    }
+
+    Context {
+        words,
+        start: start.unwrap_or(line.len()),
+        redirect,
+    }
+}
+
+/// Whether the word after `words` should be a problem's slug.
+fn expects_slug(words: &[&str]) -> bool {
+    match words {
+        [command] => matches!(*command, "f" | "fuzz" | "j" | "judge" | "solve"),
+        [.., "-p"] => words[0] == "watch",
+        ["solve", options @ ..] => {
+            let mut options = options.iter();
+            while let Some(option) = options.next() {
+                match *option {
+                    "-y" => {}
+                    "-t" => {
+                        options.next();
+                    }
+                    _ => return false,
+                }
+            }
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Completes the word at the end of `line`, with paths relative to `cwd`.
+pub fn complete(line: &str, slugs: &[String], variables: &[String], cwd: &Path) -> Completion {
+    let Context {
+        words,
+        start,
+        redirect,
+    } = context(line);
+    let word = &line[start..];
+
+    let mut candidates = if let Some(name) = word.strip_prefix('$') {
+        variables
+            .iter()
+            .filter(|v| v.starts_with(name))
+            .map(|v| format!("${}", v))
+            .collect()
+    } else if redirect {
+        paths(word, cwd)
+    } else if words.is_empty() && !word.contains('/') {
+        shell::BUILTINS
+            .iter()
+            .map(|b| b.to_string())
+            .chain(executables(word))
+            .filter(|c| c.starts_with(word))
+            .collect()
+    } else if expects_slug(&words) {
+        slugs
+            .iter()
+            .filter(|s| s.starts_with(word))
+            .cloned()
+            .collect()
+    } else {
+        paths(word, cwd)
+    };
+
+    candidates.sort();
+    candidates.dedup();
+
+    Completion {
+        start,
+        candidates,
+        selected: None,
+    }
+}
+
+/// Files and directories starting with `word`, quoted where they need to be. Directories end with
+/// `/` so completion can carry on into them.
+fn paths(word: &str, cwd: &Path) -> Vec<String> {
+    let word = unquote(word);
+    let (dir, prefix) = match word.rfind('/') {
+        Some(i) => (&word[..=i], &word[i + 1..]),
+        None => ("", word.as_str()),
+    };
+
+    let Ok(entries) = std::fs::read_dir(cwd.join(dir)) else {
+        return vec![];
+    };
+
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let hidden = name.starts_with('.') && !prefix.starts_with('.');
+            if hidden || !name.starts_with(prefix) {
+                return None;
+            }
+
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            let path = shell::quote(&format!("{}{}", dir, name));
+            Some(format!("{}{}", path, slash))
+        })
+        .collect()
+}
+
+/// The text of a word as typed so far, which may be inside quotes that haven't been closed yet.
+fn unquote(word: &str) -> String {
+    let mut text = String::new();
+    let mut quote = None;
+    let mut chars = word.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None | Some('"'), '\\') => text.extend(chars.next()),
+            (None, '\'' | '"') => quote = Some(c),
+            _ => text.push(c),
+        }
+    }
+    text
+}
+
+/// Programs on the `PATH` starting with `prefix`.
+fn executables(prefix: &str) -> Vec<String> {
+    let Some(path) = std::env::var_os("PATH") else {
+        return vec![];
+    };
+
+    std::env::split_paths(&path)
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.flatten())
+        .filter(|entry| is_executable(&entry.path()))
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| name.starts_with(prefix))
+        .collect()
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_complete() {
+        let slugs = vec!["hello-world".to_string(), "hello-ferris".to_string()];
+        let variables = vec!["s".to_string(), "selected".to_string()];
+        let cwd = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let complete = |line| complete(line, &slugs, &variables, cwd);
+
+        let completion = complete("fuzz hel");
+        assert_eq!(completion.start, 5);
+        assert_eq!(completion.candidates, ["hello-ferris", "hello-world"]);
+        assert_eq!(completion.common_prefix(), "hello-");
+
+        // Programs on the `PATH` are mixed in with the builtins.
+        let has = |line, candidate: &str| complete(line).candidates.iter().any(|c| c == candidate);
+        assert!(has("jud", "judge"));
+        assert!(has("echo a; wat", "watch"));
+        assert!(has("sh", "sh"));
+        assert_eq!(complete("solve -t 5 hello-w").candidates, ["hello-world"]);
+        assert_eq!(complete("watch -p hello-w").candidates, ["hello-world"]);
+        assert_eq!(complete("echo $sel").candidates, ["$selected"]);
+        assert_eq!(complete("cat src/ma").candidates, ["src/main.rs"]);
+        assert_eq!(complete("fuzz $s > sr").candidates, ["src/"]);
+        assert_eq!(complete("judge hello-world sr").candidates, ["src/"]);
+        assert_eq!(
+            complete("echo nothing-here").candidates,
+            Vec::<String>::new()
+        );
+        assert_eq!(complete("cat \"src/ma").candidates, ["src/main.rs"]);
+        assert_eq!(complete("cat 'sr").candidates, ["src/"]);
+
+        // Names that wouldn't read back as one word are quoted.
+        let dir = std::env::temp_dir().join(format!("fj-tui-complete-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("a dir")).unwrap();
+        std::fs::write(dir.join("it's"), "").unwrap();
+        let in_dir = |line| super::complete(line, &slugs, &variables, &dir).candidates;
+        assert_eq!(in_dir("cat a"), ["'a dir'/"]);
+        assert_eq!(in_dir("cat 'a d"), ["'a dir'/"]);
+        assert_eq!(in_dir("cat a\\ d"), ["'a dir'/"]);
+        assert_eq!(in_dir("cat it"), ["'it'\\''s'"]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
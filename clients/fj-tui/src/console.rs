@@ -13,29 +13,89 @@
  * with this program. If not, see <https://www.gnu.org/licenses/>.
  */
 
-use crate::{scroll::Scroll, utils::number_of_lines_when_broken};
+use tokio::sync::oneshot;
+
+use crate::{
+    complete::Completion,
+    history::{History, Search},
+    line_editor::LineEditor,
+    scroll::Scroll,
+    utils::number_of_lines_when_broken,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    Output,
+    /// An error from the app or a built-in command.
+    Error,
+    /// Error output of an external command.
+    Stderr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub text: String,
+    pub kind: MessageKind,
+}
+
+/// A yes or no question from a running command.
+#[derive(Debug)]
+pub struct Confirm {
+    pub prompt: String,
+    reply: oneshot::Sender<bool>,
+}
+
+impl Confirm {
+    pub fn new(prompt: &str, reply: oneshot::Sender<bool>) -> Self {
+        Self {
+            prompt: prompt.to_string(),
+            reply,
+        }
+    }
+
+    pub fn answer(self, yes: bool) {
+        // The command may have been killed while waiting.
+        let _ = self.reply.send(yes);
+    }
+}
 
 #[derive(Default)]
 pub struct ConsoleState {
-    pub messages: Vec<String>,
-    pub command_buffer: String,
-    pub command_history: Vec<String>,
+    pub messages: Vec<Message>,
+    pub command_buffer: LineEditor,
+    pub command_history: History,
     pub typing: bool,
     pub scroll: Scroll,
     pub command_history_index: usize,
     pub pre_history_command: Option<String>,
     pub console_width: usize,
+    /// Width of the prompt before the input, for counting its wrapped lines.
+    pub prompt_width: usize,
+    /// Question waiting for an answer, shown in place of the input.
+    pub confirm: Option<Confirm>,
+    /// Candidates shown after Tab found more than one.
+    pub completion: Option<Completion>,
+    /// Ctrl-R search shown in place of the input.
+    pub search: Option<Search>,
 }
 
 impl ConsoleState {
     pub fn println(&mut self, message: &str) {
-        self.messages.push(message.to_string());
-        self.recompute_scroll();
-        self.scroll.to_bottom();
+        self.push(message.to_string(), MessageKind::Output);
     }
 
     pub fn eprintln(&mut self, message: &str) {
-        self.println(&format!("ERROR: {}", message));
+        self.push(format!("ERROR: {}", message), MessageKind::Error);
+    }
+
+    pub fn print_stderr(&mut self, message: &str) {
+        self.push(message.to_string(), MessageKind::Stderr);
+    }
+
+    fn push(&mut self, text: String, kind: MessageKind) {
+        self.messages.push(Message { text, kind });
+        self.recompute_scroll();
+        self.scroll.to_bottom();
     }
 
     pub fn clear(&mut self) {
@@ -49,13 +109,23 @@ impl ConsoleState {
     }
 
     fn recompute_scroll(&mut self) {
-        // HACK: Because of line-wrapping in the ratatui paragraph, we need to approximate
-        //       the number of lines ourself.
-        let lines = self
-            .messages
+        let lines = self.messages_height()
+            + self
+                .command_buffer
+                .as_str()
+                .split('\n')
+                .map(|l| self.input_height(&format!("{} ", l)))
+                .sum::<usize>();
+        self.scroll.set_content_length(lines);
+    }
+
+    // HACK: Because of line-wrapping in the ratatui paragraph, we need to approximate
+    //       the number of lines ourself.
+    fn messages_height(&self) -> usize {
+        self.messages
             .iter()
             .map(|m| {
-                let mut m = m.clone();
+                let mut m = m.text.clone();
                 if m.ends_with('\n') {
                     m.pop();
                 }
@@ -65,23 +135,39 @@ impl ConsoleState {
                     .sum::<usize>()
             })
             .sum::<usize>()
-            + number_of_lines_when_broken(
-                &format!("> {}", self.command_buffer),
-                self.console_width,
-            );
-        self.scroll.set_content_length(lines);
+    }
+
+    /// Lines taken by `text` on an input line, after the prompt.
+    fn input_height(&self, text: &str) -> usize {
+        let prompt = " ".repeat(self.prompt_width.max(2));
+        number_of_lines_when_broken(&format!("{}{}", prompt, text), self.console_width)
+    }
+
+    /// Row of the console's content that byte `position` of the command being typed is shown on.
+    pub fn input_row(&self, position: usize) -> usize {
+        let before = &self.command_buffer.as_str()[..position];
+        let mut lines = before.split('\n').collect::<Vec<_>>();
+        let last = lines.pop().unwrap_or("");
+        self.messages_height()
+            + lines
+                .iter()
+                .map(|l| self.input_height(&format!("{} ", l)))
+                .sum::<usize>()
+            + self.input_height(last)
+            - 1
     }
 
     pub fn history_previous(&mut self) {
-        if self.pre_history_command.is_none() || self.command_history.is_empty() {
+        let history = self.command_history.entries();
+        if self.pre_history_command.is_none() || history.is_empty() {
             return;
         }
 
-        let max_idx = self.command_history.len() - 1;
+        let max_idx = history.len() - 1;
 
         if self.command_history_index == 0 {
             self.command_buffer
-                .clone_from(self.pre_history_command.as_ref().unwrap());
+                .set(self.pre_history_command.as_ref().unwrap());
             self.pre_history_command = None;
             return;
         }
@@ -90,28 +176,50 @@ impl ConsoleState {
             (self.command_history_index as i32 - 1).clamp(0, max_idx as i32) as usize;
 
         self.command_buffer
-            .clone_from(&self.command_history[max_idx - self.command_history_index]);
+            .set(&self.command_history.entries()[max_idx - self.command_history_index]);
 
         self.scroll.to_bottom();
     }
 
     pub fn history_next(&mut self) {
-        if self.command_history.is_empty() {
+        let history = self.command_history.entries();
+        if history.is_empty() {
             return;
         }
 
-        let max_idx = self.command_history.len() - 1;
+        let max_idx = history.len() - 1;
 
         if self.pre_history_command.is_none() {
-            self.pre_history_command = Some(self.command_buffer.clone());
+            self.pre_history_command = Some(self.command_buffer.as_str().to_string());
         } else {
             self.command_history_index =
                 (self.command_history_index as i32 + 1).clamp(0, max_idx as i32) as usize;
         }
 
         self.command_buffer
-            .clone_from(&self.command_history[max_idx - self.command_history_index]);
+            .set(&self.command_history.entries()[max_idx - self.command_history_index]);
 
         self.scroll.to_bottom();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_row() {
+        let mut console = ConsoleState {
+            prompt_width: 4,
+            ..Default::default()
+        };
+        console.set_console_width(10);
+        console.println("a\nb");
+        console.command_buffer.set("ls\nls aaa bbb");
+
+        assert_eq!(console.input_row(0), 2);
+        assert_eq!(console.input_row(3), 3);
+        // `bbb` wraps on to the next row.
+        assert_eq!(console.input_row(10), 4);
+    }
+}
@@ -0,0 +1,220 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    io::{self, Write},
+    sync::Arc,
+};
+
+use fj_client::Session;
+use tokio::sync::mpsc;
+
+use crate::{
+    app::Event,
+    shell::{self, OutputMode, ShellContext},
+    watch::{RunResult, WatchRun},
+};
+
+/// Runs `commands` one after another through the shell without the TUI. Command output goes to
+/// `stdout`, and errors and progress messages to `stderr`. Returns the status of the last command
+/// run, stopping early if one quits.
+pub async fn run(
+    session: Arc<Session>,
+    commands: &[String],
+    stdout: &mut impl Write,
+    stderr: &mut impl Write,
+) -> i32 {
+    let (events, mut receiver) = mpsc::unbounded_channel();
+    let ctx = ShellContext {
+        team: session.team.as_ref().map(|t| t.name.clone()),
+        session,
+        events,
+        selected: None,
+        jobs: vec![],
+        history: vec![],
+        variables: shell::SharedVariables::default(),
+        definitions: shell::SharedDefinitions::default(),
+        depth: 0,
+        directories: shell::SharedDirectories::default(),
+        auto_submissions: shell::SharedAutoSubmissions::default(),
+    };
+
+    let env = shell::Env::default();
+    let mut status = 0;
+    let mut quit = false;
+
+    for command in commands {
+        let exec = shell::exec(command, &ctx, OutputMode::ToConsole, &env);
+        tokio::pin!(exec);
+
+        // Print output as it arrives rather than when the command finishes.
+        status = loop {
+            tokio::select! {
+                output = &mut exec => break output.status,
+                Some(event) = receiver.recv() => {
+                    quit |= print(event, stdout, stderr);
+                }
+                // Commands run in their own process groups, so don't see the terminal's Ctrl-C.
+                // Dropping `exec` kills them.
+                _ = tokio::signal::ctrl_c() => {
+                    quit = true;
+                    break 130;
+                }
+            }
+        };
+
+        while let Ok(event) = receiver.try_recv() {
+            quit |= print(event, stdout, stderr);
+        }
+
+        if quit {
+            break;
+        }
+    }
+
+    status
+}
+
+/// Writes out an event from the shell. Returns whether the shell asked to quit.
+fn print(event: Event, stdout: &mut impl Write, stderr: &mut impl Write) -> bool {
+    // There's nowhere left to report a failed write to.
+    let _ = match event {
+        Event::Print(message) => writeln!(stdout, "{}", message),
+        Event::EPrint(message) | Event::Stderr(message) | Event::Info(message) => {
+            writeln!(stderr, "{}", message)
+        }
+        // There's no one to ask.
+        Event::Confirm(confirm) => {
+            let result = writeln!(stderr, "{} [y/N] n", confirm.prompt);
+            confirm.answer(false);
+            result
+        }
+        Event::SelectedProblem(reply) => {
+            let _ = reply.send(None);
+            Ok(())
+        }
+        Event::Watch(Some(run)) => print_watch_run(&run, stdout, stderr),
+        Event::Quit => return true,
+        _ => Ok(()),
+    };
+
+    false
+}
+
+/// Prints a watched solution's output once it finishes, as there's no pane to show it in.
+fn print_watch_run(
+    run: &WatchRun,
+    stdout: &mut impl Write,
+    stderr: &mut impl Write,
+) -> io::Result<()> {
+    // Submissions print their own verdicts.
+    if run.submission.is_some() {
+        return Ok(());
+    }
+
+    let Some(result) = &run.result else {
+        return Ok(());
+    };
+
+    writeln!(stderr, "{}: {}", run.file, result.summary())?;
+    if let RunResult::Finished { output, .. } = result {
+        write!(stdout, "{}", output)?;
+    }
+    Ok(())
+}
+
+/// Commands in a script, one per line unless a command like `if` or a here-document carries on
+/// over several. Blank lines and `#` comments between commands are skipped; lines inside a command
+/// are kept as written.
+pub fn parse_script(script: &str) -> Vec<String> {
+    let mut commands = vec![];
+    let mut pending = String::new();
+
+    for line in script.lines() {
+        if pending.is_empty() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            pending.push_str(trimmed);
+        } else {
+            pending.push('\n');
+            pending.push_str(line);
+        }
+        if !shell::is_incomplete(&pending) {
+            commands.push(std::mem::take(&mut pending));
+        }
+    }
+
+    // Left for the shell to report.
+    if !pending.is_empty() {
+        commands.push(pending);
+    }
+    commands
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_script() {
+        let script = "echo a\nfor q in x y\n# Each one\ndo\n  echo $q\ndone\nif true\n";
+        assert_eq!(
+            parse_script(script),
+            [
+                "echo a",
+                "for q in x y\n# Each one\ndo\n  echo $q\ndone",
+                "if true"
+            ]
+        );
+
+        let script = "cat <<EOF\n  indented\n\n# not a comment\nEOF\necho 'a\n\n  b'\n";
+        assert_eq!(
+            parse_script(script),
+            [
+                "cat <<EOF\n  indented\n\n# not a comment\nEOF",
+                "echo 'a\n\n  b'"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run() {
+        let session = Arc::new(Session::new_no_connection());
+        let commands = parse_script("# Say hello\necho hello\n\ncat\necho world\n");
+
+        let mut stdout = vec![];
+        let mut stderr = vec![];
+        let status = run(session.clone(), &commands, &mut stdout, &mut stderr).await;
+
+        assert_eq!(String::from_utf8(stdout).unwrap(), "hello\nworld\n");
+        assert_eq!(
+            String::from_utf8(stderr).unwrap(),
+            "Usage: cat <file-path>\n"
+        );
+        assert_eq!(status, 0);
+
+        let mut stdout = vec![];
+        let commands = parse_script("cat\nquit\necho unreachable");
+        let status = run(session.clone(), &commands, &mut stdout, &mut vec![]).await;
+        assert!(stdout.is_empty());
+        assert_eq!(status, 1);
+
+        let commands = parse_script("quit 3\necho unreachable");
+        let status = run(session, &commands, &mut vec![], &mut vec![]).await;
+        assert_eq!(status, 3);
+    }
+}
@@ -0,0 +1,176 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use url::Url;
+
+/// Most commands kept, dropping the oldest first.
+const MAX_ENTRIES: usize = 1000;
+
+/// Commands entered in the console, oldest first. Each is saved as it's added, so history
+/// carries over between sessions.
+#[derive(Debug, Default)]
+pub struct History {
+    entries: Vec<String>,
+    path: Option<PathBuf>,
+}
+
+/// A Ctrl-R search through the history.
+#[derive(Debug, Default)]
+pub struct Search {
+    pub query: String,
+    /// Index of the entry found, if any.
+    pub matched: Option<usize>,
+}
+
+/// Where history for a server is kept, following the XDG base directory spec.
+pub fn path(server: &Url) -> Option<PathBuf> {
+    let data = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))?;
+
+    let mut name = server.host_str().unwrap_or("local").to_string();
+    if let Some(port) = server.port() {
+        name.push_str(&format!("_{}", port));
+    }
+    let name = name.replace(
+        |c: char| !c.is_ascii_alphanumeric() && c != '.' && c != '-',
+        "_",
+    );
+
+    Some(data.join("fj-tui").join("history").join(name))
+}
+
+impl History {
+    /// Loads the history saved at `path`, which is empty if nothing has been saved yet.
+    pub fn load(path: PathBuf) -> io::Result<Self> {
+        let entries = match std::fs::read_to_string(&path) {
+            // Entries are JSON strings so commands pasted over several lines stay together.
+            Ok(text) => text
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(e),
+        };
+
+        Ok(Self {
+            entries,
+            path: Some(path),
+        })
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Adds a command, moving it to the end if it's already there, then saves.
+    pub fn push(&mut self, command: &str) -> io::Result<()> {
+        if command.trim().is_empty() {
+            return Ok(());
+        }
+
+        self.entries.retain(|entry| entry != command);
+        self.entries.push(command.to_string());
+        let excess = self.entries.len().saturating_sub(MAX_ENTRIES);
+        self.entries.drain(..excess);
+
+        self.save()
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let text = self
+            .entries
+            .iter()
+            .map(|entry| serde_json::to_string(entry).unwrap() + "\n")
+            .collect::<String>();
+        std::fs::write(path, text)
+    }
+
+    /// The newest entry before `before` containing `query`.
+    pub fn search(&self, query: &str, before: usize) -> Option<usize> {
+        self.entries[..before.min(self.entries.len())]
+            .iter()
+            .rposition(|entry| entry.contains(query))
+    }
+
+    /// The command that `!n` (numbered as by `history`) or `!!` re-runs, or `None` if the line
+    /// isn't one of those.
+    pub fn expand(&self, line: &str) -> Option<Result<String, String>> {
+        let event = line.trim().strip_prefix('!')?;
+        let index = if event == "!" {
+            self.entries.len().checked_sub(1)
+        } else {
+            event.parse::<usize>().ok()?.checked_sub(1)
+        };
+
+        Some(
+            index
+                .and_then(|i| self.entries.get(i))
+                .cloned()
+                .ok_or_else(|| format!("{}: event not found", line.trim())),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history() {
+        let path = std::env::temp_dir().join(format!("fj-tui-history-{}", std::process::id()));
+        let mut history = History::load(path.clone()).unwrap();
+
+        for command in ["fuzz a", "echo 'x\ny'", "", "judge a a.py", "fuzz a"] {
+            history.push(command).unwrap();
+        }
+        assert_eq!(history.entries(), ["echo 'x\ny'", "judge a a.py", "fuzz a"]);
+
+        assert_eq!(history.search("a", 3), Some(2));
+        assert_eq!(history.search("a", 2), Some(1));
+        assert_eq!(history.search("x", 1), Some(0));
+        assert_eq!(history.search("q", 3), None);
+
+        assert_eq!(history.expand("!2"), Some(Ok("judge a a.py".to_string())));
+        assert_eq!(history.expand(" !! "), Some(Ok("fuzz a".to_string())));
+        assert_eq!(
+            history.expand("!4"),
+            Some(Err("!4: event not found".to_string()))
+        );
+        assert_eq!(
+            history.expand("!0"),
+            Some(Err("!0: event not found".to_string()))
+        );
+        assert_eq!(history.expand("!x"), None);
+        assert_eq!(history.expand("echo !1"), None);
+
+        let loaded = History::load(path.clone()).unwrap();
+        assert_eq!(loaded.entries(), history.entries());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,113 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use tokio::task::AbortHandle;
+
+/// A command line started from the console, until it finishes or is killed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Job {
+    pub id: usize,
+    pub command: String,
+    /// Background jobs aren't stopped by Ctrl-C, and say when they finish.
+    pub background: bool,
+}
+
+/// Running jobs, oldest first.
+#[derive(Default)]
+pub struct Jobs {
+    jobs: Vec<(Job, Option<AbortHandle>)>,
+}
+
+impl Jobs {
+    /// Adds a job, returning its ID. Like other shells, IDs count up from 1 again once every job
+    /// has finished.
+    pub fn add(&mut self, command: &str, background: bool) -> usize {
+        let id = self.jobs.last().map_or(1, |(job, _)| job.id + 1);
+        let job = Job {
+            id,
+            command: command.to_string(),
+            background,
+        };
+        self.jobs.push((job, None));
+        id
+    }
+
+    /// Keeps the handle used to stop the job's task.
+    pub fn set_handle(&mut self, id: usize, handle: AbortHandle) {
+        if let Some((_, h)) = self.jobs.iter_mut().find(|(job, _)| job.id == id) {
+            *h = Some(handle);
+        }
+    }
+
+    /// Removes a job that has finished.
+    pub fn finish(&mut self, id: usize) -> Option<Job> {
+        let index = self.jobs.iter().position(|(job, _)| job.id == id)?;
+        Some(self.jobs.remove(index).0)
+    }
+
+    /// Stops a job. Dropping its task kills any commands it was running.
+    pub fn kill(&mut self, id: usize) -> Option<Job> {
+        let index = self.jobs.iter().position(|(job, _)| job.id == id)?;
+        let (job, handle) = self.jobs.remove(index);
+        if let Some(handle) = handle {
+            handle.abort();
+        }
+        Some(job)
+    }
+
+    /// The newest job not running in the background, which Ctrl-C stops.
+    pub fn foreground(&self) -> Option<&Job> {
+        self.iter().rev().find(|job| !job.background)
+    }
+
+    pub fn set_background(&mut self, id: usize, background: bool) {
+        if let Some((job, _)) = self.jobs.iter_mut().find(|(job, _)| job.id == id) {
+            job.background = background;
+        }
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Job> {
+        self.jobs.iter().map(|(job, _)| job)
+    }
+
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jobs() {
+        let mut jobs = Jobs::default();
+        assert_eq!(jobs.add("sleep 10 &", true), 1);
+        assert_eq!(jobs.add("yes", false), 2);
+        assert_eq!(jobs.foreground().unwrap().id, 2);
+
+        assert_eq!(jobs.kill(2).unwrap().command, "yes");
+        assert_eq!(jobs.foreground(), None);
+
+        jobs.set_background(1, false);
+        assert_eq!(jobs.foreground().unwrap().id, 1);
+        assert_eq!(jobs.add("echo", false), 2);
+
+        jobs.finish(1);
+        jobs.finish(2);
+        assert_eq!(jobs.len(), 0);
+        assert_eq!(jobs.add("echo", false), 1);
+    }
+}
@@ -13,32 +13,50 @@
  * with this program. If not, see <https://www.gnu.org/licenses/>.
  */
 
-use std::sync::Arc;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 
-use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
-
-use crate::{shell, state::AppState, AppStateMutex};
+use crate::{app::Effect, complete, history::Search, shell, state::AppState, view::View};
 
 #[derive(Default)]
 pub struct KeyState {
     command_buffer: String,
 }
 
-pub fn handle_press(app_state: AppStateMutex, key: KeyEvent) {
+pub fn handle_press(app_state: &mut AppState, key: KeyEvent) -> Vec<Effect> {
     if key.kind != KeyEventKind::Press {
-        return;
+        return vec![];
+    }
+
+    if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        interrupt(app_state);
+        return vec![];
     }
 
-    let typing = app_state.run_sync(|app_state| app_state.console.typing);
+    if let Some(confirm) = app_state.console.confirm.take() {
+        let yes = match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => true,
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Enter | KeyCode::Esc => false,
+            _ => {
+                app_state.console.confirm = Some(confirm);
+                return vec![];
+            }
+        };
 
-    if typing {
-        handle_typing(app_state, key);
-        return;
+        let answer = if yes { "y" } else { "n" };
+        app_state
+            .console
+            .println(&format!("{} [y/N] {}", confirm.prompt, answer));
+        confirm.answer(yes);
+        return vec![];
     }
 
-    let command_buffer = app_state.run_sync(|app_state| app_state.key.command_buffer.clone());
+    if app_state.console.typing {
+        return handle_typing(app_state, key);
+    }
 
-    let modifier = command_buffer
+    let modifier = app_state
+        .key
+        .command_buffer
         .chars()
         .take_while(|c| c.is_numeric())
         .collect::<String>()
@@ -46,165 +64,385 @@ pub fn handle_press(app_state: AppStateMutex, key: KeyEvent) {
         .ok();
 
     match key.code {
+        KeyCode::Char('q') => {
+            app_state.running = false;
+        }
         KeyCode::Char(':') => {
-            app_state.run_sync(|mut app_state| {
-                app_state.console.typing = true;
-                app_state.console.scroll.to_bottom();
-                app_state.key.command_buffer.clear();
-            });
+            app_state.console.typing = true;
+            app_state.console.scroll.to_bottom();
+            app_state.key.command_buffer.clear();
+        }
+        KeyCode::Tab => {
+            let view = app_state.view.next();
+            app_state.set_view(view);
+            app_state.key.command_buffer.clear();
+            return refresh_if_viewing_submissions(app_state);
+        }
+        KeyCode::BackTab => {
+            let view = app_state.view.previous();
+            app_state.set_view(view);
+            app_state.key.command_buffer.clear();
+            return refresh_if_viewing_submissions(app_state);
         }
         KeyCode::Left | KeyCode::Char('h') => {
-            app_state.run_sync(|mut app_state| {
-                app_state.selected_problem_borrow_mut().select_previous();
-                app_state.key.command_buffer.clear();
-                app_state.key.command_buffer.clear();
-            });
+            app_state.selected_problem_borrow_mut().select_previous();
+            app_state.key.command_buffer.clear();
+            return refresh_if_viewing_submissions(app_state);
         }
         KeyCode::Right | KeyCode::Char('l') => {
-            app_state.run_sync(|mut app_state| {
-                if let Some(s) = app_state.selected_problem_borrow_mut().selected() {
-                    if s < app_state.problems.len() - 1 {
-                        app_state.selected_problem_borrow_mut().select_next();
-                        app_state.key.command_buffer.clear();
-                    }
-                } else {
-                    app_state.selected_problem_borrow_mut().select(Some(0));
+            if let Some(s) = app_state.selected_problem_borrow_mut().selected() {
+                if s < app_state.problems.len() - 1 {
+                    app_state.selected_problem_borrow_mut().select_next();
                     app_state.key.command_buffer.clear();
                 }
-            });
+            } else {
+                app_state.selected_problem_borrow_mut().select(Some(0));
+                app_state.key.command_buffer.clear();
+            }
+            return refresh_if_viewing_submissions(app_state);
         }
         KeyCode::Down | KeyCode::Char('j') => {
-            app_state.run_sync(|mut app_state| {
+            if app_state.view == View::Submissions {
+                select_submission(app_state, modifier.unwrap_or(1) as i32);
+            } else {
                 app_state
-                    .instructions_scroll
+                    .view_scroll_mut()
                     .offset(modifier.unwrap_or(1) as i32);
-                app_state.key.command_buffer.clear();
-            });
+            }
+            app_state.key.command_buffer.clear();
         }
         KeyCode::Up | KeyCode::Char('k') => {
-            app_state.run_sync(|mut app_state| {
+            if app_state.view == View::Submissions {
+                select_submission(app_state, -(modifier.unwrap_or(1) as i32));
+            } else {
                 app_state
-                    .instructions_scroll
+                    .view_scroll_mut()
                     .offset(-(modifier.unwrap_or(1) as i32));
-                app_state.key.command_buffer.clear();
-            });
+            }
+            app_state.key.command_buffer.clear();
+        }
+        KeyCode::Char('J') => {
+            app_state
+                .view_scroll_mut()
+                .offset(modifier.unwrap_or(1) as i32);
+            app_state.key.command_buffer.clear();
+        }
+        KeyCode::Char('K') => {
+            app_state
+                .view_scroll_mut()
+                .offset(-(modifier.unwrap_or(1) as i32));
+            app_state.key.command_buffer.clear();
+        }
+        KeyCode::Char('r') if app_state.view == View::Submissions => {
+            app_state.key.command_buffer.clear();
+            return app_state.refresh_submissions();
         }
         KeyCode::Char('G') => {
-            app_state.run_sync(|mut app_state| {
-                if let Some(modifier) = modifier {
-                    app_state.instructions_scroll.set_position(modifier);
-                } else {
-                    app_state.instructions_scroll.to_bottom();
-                }
+            if let Some(modifier) = modifier {
+                app_state.view_scroll_mut().set_position(modifier);
+            } else {
+                app_state.view_scroll_mut().to_bottom();
+            }
 
-                app_state.key.command_buffer.clear();
-            });
+            app_state.key.command_buffer.clear();
         }
         KeyCode::Char(c) => {
             if c.is_ascii_alphanumeric() {
-                app_state.run_sync(|mut app_state| {
-                    app_state.key.command_buffer.push(c);
-                });
+                app_state.key.command_buffer.push(c);
             }
 
-            let command_buffer =
-                app_state.run_sync(|app_state| app_state.key.command_buffer.clone());
-
-            if command_buffer.chars().rev().take(2).collect::<String>() == "gg" {
-                app_state.run_sync(|mut app_state| {
-                    app_state.instructions_scroll.set_position(0);
-                    app_state.key.command_buffer.clear();
-                });
+            if app_state
+                .key
+                .command_buffer
+                .chars()
+                .rev()
+                .take(2)
+                .collect::<String>()
+                == "gg"
+            {
+                app_state.view_scroll_mut().set_position(0);
+                app_state.key.command_buffer.clear();
             }
         }
         KeyCode::PageUp => {
-            app_state.run_sync(|mut app_state| {
-                app_state.console.scroll.offset(-1);
-                app_state.key.command_buffer.clear();
-            });
+            app_state.console.scroll.offset(-1);
+            app_state.key.command_buffer.clear();
         }
         KeyCode::PageDown => {
-            app_state.run_sync(|mut app_state| {
-                app_state.console.scroll.offset(1);
-                app_state.key.command_buffer.clear();
-            });
+            app_state.console.scroll.offset(1);
+            app_state.key.command_buffer.clear();
         }
         _ => {}
     };
+
+    vec![]
+}
+
+/// Moves the submission selection by `offset`, resetting the detail scroll.
+fn select_submission(app_state: &mut AppState, offset: i32) {
+    if app_state.submissions.is_empty() {
+        return;
+    }
+
+    let max = app_state.submissions.len() as i32 - 1;
+    let current = app_state.selected_submission.selected().unwrap_or(0) as i32;
+    app_state
+        .selected_submission
+        .select(Some((current + offset).clamp(0, max) as usize));
+    app_state.submission_scroll.set_position(0);
 }
 
-async fn wrapped_exec(
-    app_state: Arc<tokio::sync::Mutex<AppState>>,
-    (cmd, env): (String, shell::Env),
-) {
-    shell::exec(
-        &cmd,
-        app_state.clone(),
-        shell::OutputMode::ToConsole,
-        None,
-        &env,
-    )
-    .await;
+fn refresh_if_viewing_submissions(app_state: &AppState) -> Vec<Effect> {
+    if app_state.view == View::Submissions {
+        app_state.refresh_submissions()
+    } else {
+        vec![]
+    }
 }
 
-fn handle_typing(app_state: AppStateMutex, key: KeyEvent) {
-    if key.kind != KeyEventKind::Press {
+/// Ctrl-C kills the foreground job, or otherwise throws away the command being typed.
+fn interrupt(app_state: &mut AppState) {
+    if let Some(confirm) = app_state.console.confirm.take() {
+        confirm.answer(false);
+    }
+
+    if let Some(id) = app_state.jobs.foreground().map(|job| job.id) {
+        app_state.jobs.kill(id);
+        app_state.console.println("^C");
+    } else if app_state.console.typing {
+        app_state.console.command_buffer.clear();
+        app_state.console.search = None;
+        app_state.console.command_history_index = 0;
+        app_state.console.pre_history_command = None;
+    }
+}
+
+/// Completes the word before the cursor. With several candidates, the popup is shown and each
+/// Tab after that fills in the next one.
+fn tab(app_state: &mut AppState) {
+    let console = &mut app_state.console;
+
+    if let Some(completion) = &mut console.completion {
+        let next = completion
+            .selected
+            .map_or(0, |i| (i + 1) % completion.candidates.len());
+        completion.selected = Some(next);
+        console
+            .command_buffer
+            .replace_before_cursor(completion.start, &completion.candidates[next]);
+        return;
+    }
+
+    let slugs = app_state
+        .problems
+        .iter()
+        .map(|p| p.slug.clone())
+        .collect::<Vec<_>>();
+    let mut variables = shell::SPECIAL_VARIABLES
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>();
+    variables.extend(app_state.variables.lock().unwrap().names().cloned());
+    variables.extend(std::env::vars_os().map(|(name, _)| name.to_string_lossy().into_owned()));
+    let editor = &console.command_buffer;
+    let cwd = app_state
+        .directories
+        .lock()
+        .unwrap()
+        .current()
+        .to_path_buf();
+    let completion = complete::complete(
+        &editor.as_str()[..editor.cursor()],
+        &slugs,
+        &variables,
+        &cwd,
+    );
+
+    match &completion.candidates[..] {
+        [] => {}
+        [candidate] => {
+            console
+                .command_buffer
+                .replace_before_cursor(completion.start, candidate);
+            if !candidate.ends_with('/') {
+                console.command_buffer.insert(' ');
+            }
+        }
+        _ => {
+            // Quoting can make the candidates share less than what's been typed.
+            let typed = console.command_buffer.cursor() - completion.start;
+            if completion.common_prefix().len() > typed {
+                console
+                    .command_buffer
+                    .replace_before_cursor(completion.start, completion.common_prefix());
+            }
+            console.completion = Some(completion);
+        }
+    }
+}
+
+/// Pasted text goes into the console as is, so a multi-line snippet runs as several commands.
+pub fn paste(app_state: &mut AppState, text: &str) {
+    if app_state.console.confirm.is_some() {
         return;
     }
 
+    if !app_state.console.typing {
+        app_state.console.typing = true;
+        app_state.console.scroll.to_bottom();
+        app_state.key.command_buffer.clear();
+    }
+    app_state.console.completion = None;
+    app_state
+        .console
+        .command_buffer
+        .insert_str(&text.replace("\r\n", "\n").replace('\r', "\n"));
+}
+
+/// Keys while searching the history with Ctrl-R. Returns false once the search is over and the
+/// key should be handled as usual, leaving the match to be edited or run.
+fn search_key(app_state: &mut AppState, key: KeyEvent) -> bool {
+    let console = &mut app_state.console;
+    let Some(search) = &mut console.search else {
+        return false;
+    };
+    let history = &console.command_history;
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    let alt = key.modifiers.contains(KeyModifiers::ALT);
+
     match key.code {
+        KeyCode::Char('r') if ctrl => {
+            let before = search.matched.unwrap_or(history.entries().len());
+            if let Some(older) = history.search(&search.query, before) {
+                search.matched = Some(older);
+            }
+        }
+        KeyCode::Char('g') if ctrl => console.search = None,
+        KeyCode::Esc => console.search = None,
+        KeyCode::Char(c) if !ctrl && !alt => {
+            search.query.push(c);
+            // The current match is kept for as long as it still matches.
+            let before = search.matched.map_or(history.entries().len(), |i| i + 1);
+            search.matched = history.search(&search.query, before);
+        }
+        KeyCode::Backspace => {
+            search.query.pop();
+            search.matched = history.search(&search.query, history.entries().len());
+        }
+        _ => {
+            if let Some(i) = search.matched {
+                console.command_buffer.set(&history.entries()[i]);
+            }
+            console.search = None;
+            return false;
+        }
+    }
+
+    true
+}
+
+fn handle_typing(app_state: &mut AppState, key: KeyEvent) -> Vec<Effect> {
+    if search_key(app_state, key) {
+        return vec![];
+    }
+
+    if key.code == KeyCode::Tab {
+        tab(app_state);
+        return vec![];
+    }
+    app_state.console.completion = None;
+
+    let editor = &mut app_state.console.command_buffer;
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    let alt = key.modifiers.contains(KeyModifiers::ALT);
+
+    match key.code {
+        KeyCode::Enter
+            if key
+                .modifiers
+                .intersects(KeyModifiers::SHIFT | KeyModifiers::ALT) =>
+        {
+            editor.insert('\n');
+        }
+        // An unclosed quote, `$(`, `if` or here-document, or a trailing `\`, carries on to the
+        // next line.
+        KeyCode::Enter if shell::is_incomplete(editor.as_str()) => {
+            editor.end();
+            editor.insert('\n');
+        }
         KeyCode::Enter => {
-            let mut cmd = String::new();
-
-            app_state.run_sync(|mut app_state| {
-                app_state.console.typing = false;
-                cmd.clone_from(&app_state.console.command_buffer);
-
-                let push_to_history = if let Some(last) = app_state.console.command_history.last() {
-                    last != &cmd
-                } else {
-                    true
-                };
-                if push_to_history {
-                    app_state.console.command_history.push(cmd.clone());
+            app_state.console.typing = false;
+            let mut cmd = editor.take();
+            app_state.console.command_history_index = 0;
+            app_state.console.pre_history_command = None;
+
+            match app_state.console.command_history.expand(&cmd) {
+                Some(Ok(expanded)) => cmd = expanded,
+                Some(Err(e)) => {
+                    app_state.console.println(&cmd);
+                    app_state.console.eprintln(&e);
+                    return vec![];
                 }
+                None => {}
+            }
 
-                app_state.console.messages.push(cmd.clone());
-                app_state.console.command_buffer.clear();
-                app_state.console.command_history_index = 0;
-                app_state.console.pre_history_command = None;
-            });
+            app_state.console.println(&cmd);
+            if let Err(e) = app_state.console.command_history.push(&cmd) {
+                app_state
+                    .console
+                    .eprintln(&format!("Couldn't save history: {}", e));
+            }
 
-            app_state.run_async(wrapped_exec, (cmd, shell::Env::default()));
-        }
-        KeyCode::Backspace => {
-            app_state.run_sync(|mut app_state| {
-                app_state.console.command_buffer.pop();
-            });
+            let background = shell::is_background(&cmd);
+            let job = app_state.jobs.add(&cmd, background);
+            if background {
+                app_state.console.println(&format!("[{}]", job));
+            }
+
+            return vec![Effect::Exec {
+                job,
+                command: cmd,
+                env: shell::Env::default(),
+                output_mode: shell::OutputMode::ToConsole,
+            }];
         }
+        KeyCode::Backspace if ctrl || alt => editor.delete_word_before(),
+        KeyCode::Backspace => editor.backspace(),
+        KeyCode::Delete => editor.delete(),
+        KeyCode::Left if ctrl || alt => editor.word_left(),
+        KeyCode::Right if ctrl || alt => editor.word_right(),
+        KeyCode::Left => editor.left(),
+        KeyCode::Right => editor.right(),
+        KeyCode::Home => editor.home(),
+        KeyCode::End => editor.end(),
+        KeyCode::Char('a') if ctrl => editor.home(),
+        KeyCode::Char('e') if ctrl => editor.end(),
+        KeyCode::Char('b') if ctrl => editor.left(),
+        KeyCode::Char('f') if ctrl => editor.right(),
+        KeyCode::Char('d') if ctrl => editor.delete(),
+        KeyCode::Char('w') if ctrl => editor.delete_word_before(),
+        KeyCode::Char('u') if ctrl => editor.delete_to_start(),
+        KeyCode::Char('k') if ctrl => editor.delete_to_end(),
+        KeyCode::Char('b') if alt => editor.word_left(),
+        KeyCode::Char('f') if alt => editor.word_right(),
+        KeyCode::Char('d') if alt => editor.delete_word_after(),
+        KeyCode::Char('r') if ctrl => app_state.console.search = Some(Search::default()),
+        KeyCode::Char(_) if ctrl || alt => {}
         KeyCode::Esc => {
-            app_state.run_sync(|mut app_state| {
-                app_state.console.typing = false;
-                app_state.console.command_buffer.clear();
-                app_state.console.command_history_index = 0;
-                app_state.console.pre_history_command = None;
-            });
-        }
-        KeyCode::Char(c) => {
-            app_state.run_sync(|mut app_state| {
-                app_state.console.command_buffer.push(c);
-            });
+            app_state.console.typing = false;
+            app_state.console.command_buffer.clear();
+            app_state.console.command_history_index = 0;
+            app_state.console.pre_history_command = None;
         }
+        KeyCode::Char(c) => editor.insert(c),
         KeyCode::Up => {
-            app_state.run_sync(|mut app_state| {
-                app_state.console.history_next();
-            });
+            app_state.console.history_next();
         }
         KeyCode::Down => {
-            app_state.run_sync(|mut app_state| {
-                app_state.console.history_previous();
-            });
+            app_state.console.history_previous();
         }
         _ => {}
     }
+
+    vec![]
 }
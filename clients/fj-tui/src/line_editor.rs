@@ -0,0 +1,200 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+/// Text being typed, with a cursor that can move around it. The cursor is a byte offset that's
+/// always on a character boundary.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LineEditor {
+    text: String,
+    cursor: usize,
+}
+
+impl LineEditor {
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Replaces the text, leaving the cursor at the end.
+    pub fn set(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = self.text.len();
+    }
+
+    pub fn clear(&mut self) {
+        self.set("");
+    }
+
+    /// Takes the text, leaving the editor empty.
+    pub fn take(&mut self) -> String {
+        self.cursor = 0;
+        std::mem::take(&mut self.text)
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    pub fn insert_str(&mut self, s: &str) {
+        self.text.insert_str(self.cursor, s);
+        self.cursor += s.len();
+    }
+
+    /// Replaces the text from `start` up to the cursor, as when completing a word.
+    pub fn replace_before_cursor(&mut self, start: usize, s: &str) {
+        self.text.replace_range(start..self.cursor, s);
+        self.cursor = start + s.len();
+    }
+
+    pub fn left(&mut self) {
+        self.cursor = self.previous_boundary();
+    }
+
+    pub fn right(&mut self) {
+        self.cursor = self.next_boundary();
+    }
+
+    pub fn home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.cursor = self.text.len();
+    }
+
+    /// Moves to the start of the word before the cursor.
+    pub fn word_left(&mut self) {
+        self.cursor = self.word_start(char::is_alphanumeric);
+    }
+
+    /// Moves past the end of the word after the cursor.
+    pub fn word_right(&mut self) {
+        let after = &self.text[self.cursor..];
+        let skipped = after.len()
+            - after
+                .trim_start_matches(|c: char| !c.is_alphanumeric())
+                .len();
+        let word = after[skipped..]
+            .find(|c: char| !c.is_alphanumeric())
+            .unwrap_or(after.len() - skipped);
+        self.cursor += skipped + word;
+    }
+
+    pub fn backspace(&mut self) {
+        let start = self.previous_boundary();
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    pub fn delete(&mut self) {
+        let end = self.next_boundary();
+        self.text.replace_range(self.cursor..end, "");
+    }
+
+    /// Deletes back to the previous whitespace, like Ctrl-W in other shells.
+    pub fn delete_word_before(&mut self) {
+        let start = self.word_start(|c| !c.is_whitespace());
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    /// Deletes to the end of the word after the cursor.
+    pub fn delete_word_after(&mut self) {
+        let start = self.cursor;
+        self.word_right();
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    pub fn delete_to_start(&mut self) {
+        self.text.replace_range(..self.cursor, "");
+        self.cursor = 0;
+    }
+
+    pub fn delete_to_end(&mut self) {
+        self.text.truncate(self.cursor);
+    }
+
+    fn previous_boundary(&self) -> usize {
+        self.text[..self.cursor]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.text[self.cursor..]
+            .chars()
+            .next()
+            .map_or(self.cursor, |c| self.cursor + c.len_utf8())
+    }
+
+    /// Start of the run of `in_word` characters before the cursor, skipping any others first.
+    fn word_start(&self, in_word: impl Fn(char) -> bool) -> usize {
+        let before = self.text[..self.cursor].trim_end_matches(|c: char| !in_word(c));
+        before
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| in_word(*c))
+            .last()
+            .map_or(before.len(), |(i, _)| i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_editor() {
+        let mut editor = LineEditor::default();
+        editor.set("echo héllo wörld");
+        editor.left();
+        editor.backspace();
+        assert_eq!(editor.as_str(), "echo héllo wörd");
+
+        editor.word_left();
+        assert_eq!(&editor.as_str()[editor.cursor()..], "wörd");
+        editor.word_left();
+        editor.right();
+        editor.insert('ë');
+        assert_eq!(editor.as_str(), "echo hëéllo wörd");
+
+        editor.word_right();
+        editor.delete_word_after();
+        assert_eq!(editor.as_str(), "echo hëéllo");
+
+        editor.delete_word_before();
+        assert_eq!(editor.as_str(), "echo ");
+
+        editor.insert_str("a b");
+        editor.home();
+        editor.delete();
+        editor.end();
+        editor.replace_before_cursor(4, "X");
+        assert_eq!(editor.as_str(), "cho X");
+
+        editor.left();
+        editor.delete_to_start();
+        editor.insert_str("> ");
+        editor.delete_to_end();
+        assert_eq!(editor.take(), "> ");
+        assert_eq!((editor.as_str(), editor.cursor()), ("", 0));
+    }
+}
@@ -13,44 +13,56 @@
  * with this program. If not, see <https://www.gnu.org/licenses/>.
  */
 
-use api::connect_to_web_socket;
-use ratatui::crossterm;
+use app::Effect;
+use history::History;
 use state::AppState;
-use std::time::Instant;
-use std::{error::Error, io, sync::Arc};
-use tokio::sync::Mutex;
+use std::{error::Error, io, path::PathBuf, sync::Arc};
+use tokio::sync::mpsc;
 use url::Url;
 
 use ratatui::{
     backend::CrosstermBackend,
     crossterm::{
-        event::{Event, KeyCode},
+        event::{
+            DisableBracketedPaste, EnableBracketedPaste, KeyboardEnhancementFlags,
+            PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+        },
         execute,
-        terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+        terminal::{
+            disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen,
+            LeaveAlternateScreen,
+        },
     },
     Terminal,
 };
 
-use crate::state::AppStateMutex;
-
-mod api;
-mod auth;
-mod clock;
+mod app;
+mod complete;
 mod console;
+mod headless;
+mod history;
+mod job;
 mod key;
+mod line_editor;
 mod md;
-mod problem;
+mod rc;
 mod scroll;
 mod shell;
 mod state;
+mod toast;
 mod ui;
 mod utils;
+mod view;
+mod watch;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    mode: Option<Mode>,
+
     /// URL of the server to connect to.
     #[arg(short, long)]
     server: String,
@@ -63,91 +75,135 @@ struct Args {
     #[arg(short, long, default_value_t = String::from(""))]
     password: String,
 
-    /// Command to run when a new problem is recieved.
-    /// `$q` will be set to the problem ID. (remember to escape `$` in your terminal)
-    /// Example: `--on-recieve-problem "mkdir prob ; fuzz \$q > prob/\$q.txt"`
+    /// Run each line of a script without the TUI, then exit with the last command's status.
     #[arg(long)]
-    on_recieve_problem: Option<String>,
-    // #[arg(long)]
-    // auto_submit: Option<String>,
-    //
-    // #[arg(long)]
-    // watch: Option<String>,
-}
+    script: Option<PathBuf>,
 
-async fn get_questions(app_state: Arc<Mutex<AppState>>, _: ()) {
-    let problems = app_state
-        .lock()
-        .await
-        .session
-        .fetch_all_problems()
-        .await
-        .expect("Failed to fetch problems");
-
-    let cmds = app_state.lock().await.on_new_problem.clone();
-
-    for cmd in cmds {
-        for problem in &problems {
-            let mut env = shell::Env::default();
-            env.insert("q".to_string(), problem.slug.clone());
-
-            let _ = shell::exec(
-                &cmd,
-                app_state.clone(),
-                shell::OutputMode::Piped,
-                None,
-                &env,
-            )
-            .await;
-        }
-    }
-
-    let mut app_state = app_state.lock().await;
+    /// Re-run a solution against the selected problem's input whenever it's saved, showing the
+    /// output in a pane. Given a directory, runs whichever file in it was saved.
+    #[arg(long)]
+    watch: Option<PathBuf>,
 
-    app_state.problems = problems;
-    app_state.selected_problem_borrow_mut().select(Some(0));
+    /// Submit the output of a watched solution when it runs successfully, at most once every 30
+    /// seconds per problem.
+    #[arg(long, requires = "watch")]
+    auto_submit: bool,
 }
 
-async fn start_web_socket(app_state: Arc<Mutex<AppState>>, _: ()) {
-    let socket_addr = {
-        let app_state = app_state.lock().await;
-        let mut addr = app_state.session.server.clone().join("/graphql").expect("Invalid WebSocket URL");
-        if addr.scheme() == "http" {
-            addr.set_scheme("ws").unwrap();
-        } else if addr.scheme() == "https" {
-            addr.set_scheme("wss").unwrap();
-        }
-        addr
-    };
-
-    connect_to_web_socket(socket_addr.as_str(), app_state.clone()).await;
+#[derive(Subcommand, Debug)]
+enum Mode {
+    /// Run a command without the TUI, then exit with its status.
+    Exec { command: String },
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
 
     let server = args.server;
-    let creds = auth::Credentials::new(&args.username, &args.password);
+    let creds = fj_client::Credentials::new(&args.username, &args.password);
 
     if Url::parse(&server).is_err() {
         eprintln!("Invalid server URL: {}", server);
         return Ok(());
     }
 
-    let app_state = AppStateMutex::new(server.clone(), creds.clone());
+    let headless_commands = match (args.mode, args.script) {
+        (Some(Mode::Exec { command }), _) => Some(vec![command]),
+        (None, Some(path)) => match std::fs::read_to_string(&path) {
+            Ok(script) => Some(headless::parse_script(&script)),
+            Err(e) => {
+                eprintln!("Couldn't read {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        },
+        (None, None) => None,
+    };
+
+    if headless_commands.is_none() {
+        println!("Connecting to server: {}...", server);
+    }
 
-    app_state.run_sync(|mut app_state| {
-        if let Some(on_recieve_problem) = args.on_recieve_problem {
-            app_state.on_new_problem.push(on_recieve_problem);
+    let session = match fj_client::Session::new(&server, creds).await {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Connection Error: {}", e);
+            std::process::exit(1);
         }
-    });
+    };
+
+    if let Some(mut commands) = headless_commands {
+        commands.splice(..0, rc::commands());
+        let status = headless::run(
+            Arc::new(session),
+            &commands,
+            &mut io::stdout(),
+            &mut io::stderr(),
+        )
+        .await;
+        std::process::exit(status);
+    }
 
-    app_state.run_async(get_questions, ());
-    app_state.run_async(start_web_socket, ());
+    let mut app_state = AppState::new(session);
+
+    if let Some(path) = Url::parse(&server).ok().as_ref().and_then(history::path) {
+        match History::load(path) {
+            Ok(history) => app_state.console.command_history = history,
+            Err(e) => app_state
+                .console
+                .eprintln(&format!("Couldn't load history: {}", e)),
+        }
+    }
+
+    let (events, mut receiver) = mpsc::unbounded_channel();
+
+    // Finished before the problems are fetched, so the hook for new problems is defined in time.
+    let ctx = app::shell_context(&app_state, &events);
+    for command in rc::commands() {
+        shell::exec(
+            &command,
+            &ctx,
+            shell::OutputMode::ToConsole,
+            &shell::Env::default(),
+        )
+        .await;
+    }
+
+    if let Some(path) = args.watch {
+        let command = if args.auto_submit {
+            "watch -a \"$path\" &"
+        } else {
+            "watch \"$path\" &"
+        };
+        let mut env = shell::Env::default();
+        env.insert("path".to_string(), path.display().to_string());
+
+        let effect = Effect::Exec {
+            job: app_state.jobs.add(command, true),
+            command: command.to_string(),
+            env,
+            output_mode: shell::OutputMode::ToConsole,
+        };
+        app::start(effect, &mut app_state, &events);
+    }
+
+    app::start(Effect::FetchCompetition, &mut app_state, &events);
+    app::start(Effect::FetchProblems, &mut app_state, &events);
+    app::spawn_subscriptions(app_state.session.clone(), events.clone());
+    app::spawn_input_reader(events.clone());
+    app::spawn_ticker(events.clone());
 
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    execute!(stdout, EnterAlternateScreen, EnableBracketedPaste)?;
+    // Lets terminals that support it tell Shift+Enter apart from Enter.
+    let enhanced_keys = supports_keyboard_enhancement().unwrap_or(false);
+    if enhanced_keys {
+        execute!(
+            stdout,
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+        )?;
+    }
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
@@ -160,38 +216,22 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let version = env!("CARGO_PKG_VERSION");
 
-    app_state.println(&format!("{}\nv{}\n", SPLASH_TEXT, version));
-
-    let tick_rate = std::time::Duration::from_millis(250);
-    let mut last_tick = Instant::now();
+    app_state
+        .console
+        .println(&format!("{}\nv{}\n", SPLASH_TEXT, version));
 
-    loop {
-        let _ = terminal.draw(|f| app_state.run_sync(|app_state| ui::draw(f, app_state)));
+    let result = app::run(&mut terminal, &mut app_state, events, &mut receiver).await;
 
-        let timeout = tick_rate.saturating_sub(last_tick.elapsed());
-        if crossterm::event::poll(timeout)? {
-            if let Event::Key(key) = crossterm::event::read()? {
-                let typing = app_state.run_sync(|app_state| app_state.console.typing);
-                if !typing && key.code == KeyCode::Char('q') {
-                    break;
-                }
-
-                key::handle_press(app_state.clone(), key);
-            }
-        }
-
-        if last_tick.elapsed() >= tick_rate {
-            last_tick = Instant::now();
-        }
-
-        let running = app_state.run_sync(|app_state| app_state.running);
-        if !running {
-            break;
-        }
+    if enhanced_keys {
+        execute!(terminal.backend_mut(), PopKeyboardEnhancementFlags)?;
     }
-
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    execute!(
+        terminal.backend_mut(),
+        DisableBracketedPaste,
+        LeaveAlternateScreen
+    )?;
     terminal.show_cursor()?;
+    result?;
     Ok(())
 }
@@ -138,7 +138,7 @@ fn join<'a>(contents: &mut Vec<Line<'a>>, lines: Vec<Line<'a>>) {
     }
 }
 
-fn render_children(children: &[mdast::Node]) -> Vec<Line> {
+fn render_children(children: &[mdast::Node]) -> Vec<Line<'_>> {
     let mut contents: Vec<Line> = vec![];
 
     for child in children {
@@ -0,0 +1,45 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Startup files, run through the shell before anything else to set up aliases, functions and
+//! variables.
+
+use std::path::{Path, PathBuf};
+
+use crate::shell;
+
+/// `$XDG_CONFIG_HOME/fj-tui/rc` (`~/.config` by default) for everything, then `.fjrc` in the
+/// current directory for a particular competition. Only files that exist are returned.
+pub fn paths() -> Vec<PathBuf> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")));
+
+    config
+        .map(|config| config.join("fj-tui").join("rc"))
+        .into_iter()
+        .chain([PathBuf::from(".fjrc")])
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Commands that run the startup files.
+pub fn commands() -> Vec<String> {
+    paths()
+        .iter()
+        .map(|path| format!("source {}", shell::quote(&path.display().to_string())))
+        .collect()
+}
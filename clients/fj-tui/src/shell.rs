@@ -13,481 +13,1130 @@
  * with this program. If not, see <https://www.gnu.org/licenses/>.
  */
 
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::{Arc, Mutex},
+};
 
 use async_recursion::async_recursion;
-use tokio::{io::AsyncWriteExt, process::Command};
-
-use crate::state::AppState;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt},
+    process,
+    sync::{mpsc, oneshot},
+};
+
+use fj_client::{Error, Session};
+
+use crate::{
+    app::{self, Event, EventSender},
+    console::Confirm,
+    job::Job,
+};
+
+use ast::{
+    AndOr, Command, Compound, Connector, List, ListItem, Pipeline, Redirect, RedirectKind,
+    SimpleCommand, Word, WordPart,
+};
+
+pub use definitions::SharedDefinitions;
+pub use directories::{display as display_path, SharedDirectories};
+use output::Stream;
+pub use output::{Output, OutputMode};
+pub use variables::SharedVariables;
+pub use watch::SharedAutoSubmissions;
+
+mod ast;
+mod braces;
+mod condition;
+mod definitions;
+mod directories;
+mod glob;
+mod lexer;
+mod output;
+mod parser;
+mod solve;
+mod variables;
+mod watch;
 
 pub type Env = HashMap<String, String>;
 
+/// What a command reads from: the previous command in the pipeline, or a file.
+type Input = Box<dyn AsyncRead + Send + Unpin>;
+
+/// Bytes buffered between two commands in a pipeline before the first has to wait.
+const PIPE_CAPACITY: usize = 64 * 1024;
+
+/// Most function calls inside each other, so a function calling itself forever is stopped.
+const MAX_FUNCTION_DEPTH: usize = 25;
+
+/// Function run for each problem when the problem list arrives, with its slug as `$1` and `$q`.
+pub const NEW_PROBLEM_HOOK: &str = "on_new_problem";
+
+/// Reads of an external command's output waiting to be printed.
+const OUTPUT_BUFFER: usize = 64;
+
+/// What a command can see of the app while it runs. Output and changes are sent back to the
+/// event loop as events.
+#[derive(Clone)]
+pub struct ShellContext {
+    pub session: Arc<Session>,
+    pub events: EventSender,
+    /// Slug of the problem selected when the command was entered, for `$s`.
+    pub selected: Option<String>,
+    /// Other jobs running when the command was entered.
+    pub jobs: Vec<Job>,
+    /// Commands entered in the console, oldest first, for `history`.
+    pub history: Vec<String>,
+    /// Variables set during the session.
+    pub variables: SharedVariables,
+    /// Name of the user's team, for `$team`.
+    pub team: Option<String>,
+    /// Aliases and functions defined during the session.
+    pub definitions: SharedDefinitions,
+    /// How many function calls the command is inside.
+    pub depth: usize,
+    /// The working directory, changed with `cd`.
+    pub directories: SharedDirectories,
+    /// What `watch -a` has submitted during the session.
+    pub auto_submissions: SharedAutoSubmissions,
+}
+
+impl ShellContext {
+    /// The working directory now, which may have changed since the command started.
+    pub fn cwd(&self) -> PathBuf {
+        self.directories.lock().unwrap().current().to_path_buf()
+    }
+
+    /// Where a path typed in a command refers to.
+    pub fn path(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.directories.lock().unwrap().resolve(path)
+    }
+
+    pub fn send(&self, event: Event) {
+        // Only fails once the app is shutting down.
+        let _ = self.events.send(event);
+    }
+
+    pub fn eprintln(&self, message: &str) {
+        self.send(Event::EPrint(message.to_string()));
+    }
+
+    /// Progress messages, which shouldn't end up in the output of a script.
+    pub fn info(&self, message: &str) {
+        self.send(Event::Info(message.to_string()));
+    }
+
+    /// Asks the user a yes or no question, taking no for an answer if they can't be asked.
+    pub async fn confirm(&self, prompt: &str) -> bool {
+        let (reply, answer) = oneshot::channel();
+        self.send(Event::Confirm(Confirm::new(prompt, reply)));
+        answer.await.unwrap_or(false)
+    }
+
+    /// The problem selected now, which may have changed since the command started.
+    pub async fn selected_problem(&self) -> Option<String> {
+        let (reply, slug) = oneshot::channel();
+        self.send(Event::SelectedProblem(reply));
+        slug.await.ok().flatten()
+    }
+
+    /// Shows an error from the server.
+    pub fn report(&self, e: &Error) {
+        self.send(app::error_event(e));
+    }
+
+    /// A context for a substitution or a command piped into another, which gets its own copy of
+    /// the working directory and variables so a `cd` or `set` inside it doesn't change the
+    /// session's.
+    fn subshell(&self) -> ShellContext {
+        let directories = self.directories.lock().unwrap().clone();
+        let variables = self.variables.lock().unwrap().clone();
+        ShellContext {
+            directories: Arc::new(Mutex::new(directories)),
+            variables: Arc::new(Mutex::new(variables)),
+            ..self.clone()
+        }
+    }
+}
+
+/// Whether a command line ends with `&`, so shouldn't hold up the console.
+pub fn is_background(command: &str) -> bool {
+    parser::parse_line(command).is_ok_and(|list| list.0.last().is_some_and(|item| item.background))
+}
+
+/// Whether a command line needs more input to finish it, like an `if` without its `fi`.
+pub fn is_incomplete(command: &str) -> bool {
+    matches!(
+        parser::parse_line(command),
+        Err(parser::ParseError::Incomplete(_))
+    )
+}
+
+/// Parses and runs a command line.
+pub async fn exec(command: &str, ctx: &ShellContext, output_mode: OutputMode, env: &Env) -> Output {
+    let mut output = Output::new(output_mode, ctx.events.clone());
+    match parser::parse_line(command) {
+        Ok(list) => eval_list(&list, ctx, None, &output, env).await,
+        Err(e) => {
+            ctx.eprintln(&format!("Syntax error: {}", e));
+            output.status = 2;
+            output
+        }
+    }
+}
+
+/// Runs a list. Any `input` goes to the first command, which is the only one that can read it.
+/// Output goes wherever `parent`'s does.
 #[async_recursion]
-pub async fn exec(
-    command: &str,
-    app_state: Arc<tokio::sync::Mutex<AppState>>,
-    output_mode: OutputMode,
-    piped_input: Option<String>,
+async fn eval_list(
+    list: &List,
+    ctx: &ShellContext,
+    input: Option<Input>,
+    parent: &Output,
+    env: &Env,
+) -> Output {
+    eval_items(&list.0, ctx, input, parent, env).await
+}
+
+/// Runs list items in order. Background items run alongside everything after them.
+#[async_recursion]
+async fn eval_items(
+    items: &[ListItem],
+    ctx: &ShellContext,
+    input: Option<Input>,
+    parent: &Output,
     env: &Env,
 ) -> Output {
-    let mut output = Output::new(output_mode, app_state.clone());
+    let Some((item, rest)) = items.split_first() else {
+        return parent.inner();
+    };
+
+    // Aliases are expanded just before running, so ones defined earlier in the line apply.
+    let and_or = ctx.definitions.lock().unwrap().expand_aliases(&item.and_or);
 
-    if let Some((l, r)) = split_by_oper(command, ";", false) {
-        exec(&l, app_state.clone(), output_mode, None, env).await;
-        exec(&r, app_state.clone(), output_mode, None, env).await;
+    if item.background {
+        let (mut output, rest_output) = tokio::join!(
+            eval_and_or(&and_or, ctx, input, parent, env),
+            eval_items(rest, ctx, None, parent, env)
+        );
+        output.status = 0;
+        output.append(rest_output);
         return output;
     }
 
-    if let Some((l, r)) = split_by_oper(command, "&", false) {
-        tokio::join! {
-            exec(&l, app_state.clone(), output_mode, None, env),
-            exec(&r, app_state.clone(), output_mode, None, env)
+    let mut output = eval_and_or(&and_or, ctx, input, parent, env).await;
+    if !rest.is_empty() {
+        output.append(eval_items(rest, ctx, None, parent, env).await);
+    }
+    output
+}
+
+async fn eval_and_or(
+    and_or: &AndOr,
+    ctx: &ShellContext,
+    input: Option<Input>,
+    parent: &Output,
+    env: &Env,
+) -> Output {
+    let mut output = eval_pipeline(&and_or.first, ctx, input, parent, env).await;
+
+    for (connector, pipeline) in &and_or.rest {
+        let run = match connector {
+            Connector::And => output.status == 0,
+            Connector::Or => output.status != 0,
         };
+
+        if run {
+            output.append(eval_pipeline(pipeline, ctx, None, parent, env).await);
+        }
+    }
+
+    output
+}
+
+async fn eval_pipeline(
+    pipeline: &Pipeline,
+    ctx: &ShellContext,
+    input: Option<Input>,
+    parent: &Output,
+    env: &Env,
+) -> Output {
+    let output = eval_stages(&pipeline.0, ctx, input, parent, env).await;
+    ctx.variables.lock().unwrap().status = output.status;
+    output
+}
+
+/// Runs every command of a pipeline at once, each reading the previous one's output as it's
+/// written, so large outputs never have to be held in memory.
+#[async_recursion]
+async fn eval_stages(
+    commands: &[Command],
+    ctx: &ShellContext,
+    input: Option<Input>,
+    parent: &Output,
+    env: &Env,
+) -> Output {
+    let (first, rest) = commands.split_first().expect("Pipelines aren't empty");
+
+    if rest.is_empty() {
+        return eval_command(first, ctx, input, parent.inner(), env).await;
+    }
+
+    let (writer, reader) = tokio::io::duplex(PIPE_CAPACITY);
+    let output = parent.piped(writer);
+
+    let subshell = ctx.subshell();
+    let (_, output) = tokio::join!(
+        eval_command(first, &subshell, input, output, env),
+        eval_stages(rest, ctx, Some(Box::new(reader)), parent, env)
+    );
+    output
+}
+
+async fn eval_command(
+    command: &Command,
+    ctx: &ShellContext,
+    stdin: Option<Input>,
+    mut output: Output,
+    env: &Env,
+) -> Output {
+    match command {
+        Command::Simple(command) => eval_simple(command, ctx, stdin, output, env).await,
+        Command::Compound(compound, redirects) => {
+            eval_compound(compound, redirects, ctx, stdin, output, env).await
+        }
+        Command::Function { name, body } => {
+            ctx.definitions
+                .lock()
+                .unwrap()
+                .define_function(name, body.clone());
+            output.finish().await;
+            output
+        }
+    }
+}
+
+async fn eval_simple(
+    command: &SimpleCommand,
+    ctx: &ShellContext,
+    mut stdin: Option<Input>,
+    mut output: Output,
+    env: &Env,
+) -> Output {
+    let mut args = vec![];
+    for word in &command.words {
+        args.extend(expand_word(word, ctx, env).await);
+    }
+
+    // Like an assignment in other shells, `set` fails if a substitution in it did.
+    let substituted = command.words.iter().any(|word| {
+        word.0
+            .iter()
+            .any(|part| matches!(part, WordPart::Substitution { .. }))
+    });
+    let substitution_status = ctx.variables.lock().unwrap().status;
+    let assignment = substituted && args.first().is_some_and(|name| name == "set");
+
+    if !apply_redirects(&command.redirects, ctx, env, &mut output, &mut stdin).await {
+        output.finish().await;
+        return output;
+    }
+
+    let function = args
+        .first()
+        .and_then(|name| ctx.definitions.lock().unwrap().function(name).cloned());
+    let mut output = match function {
+        Some(body) => call_function(&body, &args, ctx, stdin, output, env).await,
+        None => run_command(args, ctx, output, stdin, env).await,
+    };
+    if assignment && output.status == 0 {
+        output.status = substitution_status;
+    }
+    output.finish().await;
+    output
+}
+
+/// Runs a function's body, with its arguments as `$1`, `$2` and so on.
+async fn call_function(
+    body: &List,
+    args: &[String],
+    ctx: &ShellContext,
+    stdin: Option<Input>,
+    mut output: Output,
+    env: &Env,
+) -> Output {
+    if ctx.depth >= MAX_FUNCTION_DEPTH {
+        output
+            .eprintln(&format!("{}: too many nested function calls", args[0]))
+            .await;
+        output.status = 1;
+        return output;
+    }
+
+    let mut env = env.clone();
+    for (i, arg) in args.iter().enumerate().skip(1) {
+        env.insert(i.to_string(), arg.clone());
+    }
+    env.insert("#".to_string(), (args.len() - 1).to_string());
+    env.insert("@".to_string(), args[1..].join(" "));
+
+    let ctx = ShellContext {
+        depth: ctx.depth + 1,
+        ..ctx.clone()
+    };
+    output.status = eval_body(body, &ctx, stdin, &mut output, &env).await;
+    output
+}
+
+/// Runs a conditional, loop or group. Any input goes to the first command run inside it.
+async fn eval_compound(
+    compound: &Compound,
+    redirects: &[Redirect],
+    ctx: &ShellContext,
+    mut stdin: Option<Input>,
+    mut output: Output,
+    env: &Env,
+) -> Output {
+    if !apply_redirects(redirects, ctx, env, &mut output, &mut stdin).await {
+        output.finish().await;
         return output;
     }
 
-    if let Some((l, r)) = split_by_oper(command, "&&", false) {
-        let l_output = exec(&l, app_state.clone(), output_mode, None, env).await;
-        if l_output.status == 0 {
-            let r_output = exec(&r, app_state.clone(), output_mode, None, env).await;
-            return r_output;
+    output.status = match compound {
+        Compound::If {
+            branches,
+            otherwise,
+        } => 'run: {
+            for (condition, body) in branches {
+                if eval_body(condition, ctx, stdin.take(), &mut output, env).await == 0 {
+                    break 'run eval_body(body, ctx, None, &mut output, env).await;
+                }
+            }
+
+            match otherwise {
+                Some(body) => eval_body(body, ctx, None, &mut output, env).await,
+                None => 0,
+            }
+        }
+        Compound::For { name, words, body } => {
+            let mut values = vec![];
+            for word in words {
+                values.extend(expand_word(word, ctx, env).await);
+            }
+
+            let mut env = env.clone();
+            let mut status = 0;
+            for value in values {
+                env.insert(name.clone(), value);
+                status = eval_body(body, ctx, stdin.take(), &mut output, &env).await;
+                if output.is_broken() {
+                    break;
+                }
+                tokio::task::yield_now().await;
+            }
+            status
+        }
+        Compound::While { condition, body } => {
+            let mut status = 0;
+            while eval_body(condition, ctx, stdin.take(), &mut output, env).await == 0
+                && !output.is_broken()
+            {
+                status = eval_body(body, ctx, None, &mut output, env).await;
+                // Builtins alone might never wait, which would leave no chance to stop the loop.
+                tokio::task::yield_now().await;
+            }
+            status
+        }
+        Compound::Group(list) => eval_body(list, ctx, stdin, &mut output, env).await,
+    };
+
+    output.finish().await;
+    output
+}
+
+/// Runs a list inside a conditional, loop or function, sending its output wherever the enclosing
+/// command's goes as it's written. Returns its status.
+async fn eval_body(
+    list: &List,
+    ctx: &ShellContext,
+    input: Option<Input>,
+    output: &mut Output,
+    env: &Env,
+) -> i32 {
+    let body = eval_list(list, ctx, input, output, env).await;
+    output.stdout.push_str(&body.stdout);
+    body.status
+}
+
+/// Applies redirects in order, stopping at the first that fails.
+async fn apply_redirects(
+    redirects: &[Redirect],
+    ctx: &ShellContext,
+    env: &Env,
+    output: &mut Output,
+    stdin: &mut Option<Input>,
+) -> bool {
+    for redirect in redirects {
+        let mut fields = expand_word(&redirect.target, ctx, env).await;
+        let result = if redirect.kind == RedirectKind::HereDoc {
+            apply_redirect(redirect, &fields.join(" "), ctx, output, stdin).await
+        } else if fields.len() == 1 {
+            let target = fields.pop().unwrap();
+            apply_redirect(redirect, &target, ctx, output, stdin).await
+        } else if fields.is_empty() {
+            Err("ambiguous redirect".to_string())
         } else {
-            return l_output;
+            Err(format!("{}: ambiguous redirect", fields.join(" ")))
+        };
+
+        if let Err(e) = result {
+            output.eprintln(&e).await;
+            output.status = 1;
+            return false;
         }
     }
 
-    if let Some((l, r)) = split_by_oper(command, "|", true) {
-        app_state
-            .lock()
-            .await
-            .console
-            .println(&format!("running piped {command}"));
+    true
+}
 
-        let l_output = exec(&l, app_state.clone(), OutputMode::Piped, None, env).await;
-        let r_output = exec(
-            &r,
-            app_state.clone(),
-            output_mode,
-            Some(l_output.stdout),
-            env,
-        )
-        .await;
-        return r_output;
+/// Points `output` at the redirect's target. Files are created straight away, like other shells,
+/// even if a later redirect takes their place.
+async fn apply_redirect(
+    redirect: &Redirect,
+    target: &str,
+    ctx: &ShellContext,
+    output: &mut Output,
+    stdin: &mut Option<Input>,
+) -> Result<(), String> {
+    let path = ctx.path(target);
+
+    match (redirect.kind, redirect.fd) {
+        (RedirectKind::HereDoc, 0) => {
+            *stdin = Some(Box::new(std::io::Cursor::new(
+                target.to_string().into_bytes(),
+            )));
+        }
+        (RedirectKind::Read, 0) => {
+            let file = tokio::fs::File::open(&path)
+                .await
+                .map_err(|e| format!("{}: {}", target, e))?;
+            *stdin = Some(Box::new(file));
+        }
+        (RedirectKind::Write | RedirectKind::Append, fd @ (1 | 2)) => {
+            let result = if redirect.kind == RedirectKind::Write {
+                tokio::fs::File::create(&path).await
+            } else {
+                tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .await
+            };
+            let file = result.map_err(|e| format!("{}: {}", target, e))?;
+
+            let stream = if fd == 1 {
+                Stream::Stdout
+            } else {
+                Stream::Stderr
+            };
+            output.redirect_to_file(stream, path, file);
+        }
+        // Like other shells, the stream goes wherever the other one goes *now*.
+        (RedirectKind::Duplicate, fd) => match (fd, target) {
+            (1, "1") | (2, "2") => {}
+            (1, "2") => output.duplicate(Stream::Stdout, Stream::Stderr),
+            (2, "1") => output.duplicate(Stream::Stderr, Stream::Stdout),
+            _ => return Err(format!("{}>&{}: unsupported redirect", fd, target)),
+        },
+        (_, fd) => return Err(format!("{}: unsupported stream", fd)),
     }
 
-    if let Some((l, r)) = split_by_oper(command, ">", true) {
-        let l_output = exec(&l, app_state.clone(), OutputMode::Piped, None, env).await;
+    Ok(())
+}
 
-        write_file(
-            app_state.clone(),
-            (
-                PathBuf::from(process_arg(&r, app_state.clone(), env).await),
-                l_output.stdout.clone(),
-            ),
-        )
-        .await;
+/// Expands braces, a leading `~`, variables, substitutions and filename patterns in a word.
+/// Unquoted expansions are split on whitespace, so one word can become several arguments, or none.
+async fn expand_word(word: &Word, ctx: &ShellContext, env: &Env) -> Vec<String> {
+    let mut fields = vec![];
+    for word in braces::expand(word) {
+        fields.extend(expand_fields(&expand_tilde(word), ctx, env).await);
+    }
+    fields
+}
 
-        return output;
+/// Replaces `~` at the start of a word with the home directory, unless it's quoted.
+fn expand_tilde(mut word: Word) -> Word {
+    let Some(WordPart::Literal(text)) = word.0.first() else {
+        return word;
+    };
+    if text != "~" && !text.starts_with("~/") {
+        return word;
     }
+    let Ok(home) = std::env::var("HOME") else {
+        return word;
+    };
 
-    let words = tokenize(command);
-    let command = words.clone().into_iter().next().unwrap_or_default();
-    let mut args = words.into_iter().skip(1).collect::<Vec<String>>();
+    let rest = text[1..].to_string();
+    word.0.splice(
+        ..1,
+        [WordPart::Quoted(home), WordPart::Literal(rest)]
+            .into_iter()
+            .filter(|part| part != &WordPart::Literal(String::new())),
+    );
+    word
+}
+
+async fn expand_fields(word: &Word, ctx: &ShellContext, env: &Env) -> Vec<String> {
+    // Each field is kept alongside a pattern with its quoted characters escaped.
+    let mut fields = vec![];
+    let mut current: Option<(String, String)> = None;
 
-    for arg in args.iter_mut() {
-        *arg = process_arg(arg, app_state.clone(), env).await;
+    fn push(current: &mut Option<(String, String)>, text: &str, quoted: bool) {
+        let (field, pattern) = current.get_or_insert_with(Default::default);
+        field.push_str(text);
+        if quoted {
+            pattern.push_str(&glob::escape(text));
+        } else {
+            pattern.push_str(text);
+        }
     }
 
+    for part in &word.0 {
+        let (text, quoted) = match part {
+            WordPart::Literal(text) => {
+                push(&mut current, text, false);
+                continue;
+            }
+            WordPart::Quoted(text) => (text.clone(), true),
+            WordPart::Variable { name, quoted } => (variable(name, ctx, env), *quoted),
+            WordPart::Substitution { list, quoted } => {
+                let parent = Output::new(OutputMode::Piped, ctx.events.clone());
+                let output = eval_list(list, &ctx.subshell(), None, &parent, env).await;
+                ctx.variables.lock().unwrap().status = output.status;
+                (output.stdout.trim_end_matches('\n').to_string(), *quoted)
+            }
+        };
+
+        if quoted {
+            push(&mut current, &text, true);
+            continue;
+        }
+
+        if text.starts_with(char::is_whitespace) {
+            fields.extend(current.take());
+        }
+        for (i, field) in text.split_whitespace().enumerate() {
+            if i > 0 {
+                fields.extend(current.take());
+            }
+            push(&mut current, field, false);
+        }
+        if text.ends_with(char::is_whitespace) {
+            fields.extend(current.take());
+        }
+    }
+    fields.extend(current);
+
+    // Patterns that match nothing are kept as they are, like other shells.
+    let mut args = vec![];
+    for (field, pattern) in fields {
+        let matches = if glob::is_pattern(&pattern) {
+            glob::glob(&pattern, &ctx.cwd())
+        } else {
+            vec![]
+        };
+        if matches.is_empty() {
+            args.push(field);
+        } else {
+            args.extend(matches);
+        }
+    }
+    args
+}
+
+/// Quotes a value so it reads back as the same word.
+pub fn quote(value: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "_-./:@%+,".contains(c);
+    if !value.is_empty() && value.chars().all(plain) {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+/// Commands run by the shell itself, for completion. Short aliases are left out.
+pub const BUILTINS: &[&str] = &[
+    "help", "fuzz", "judge", "solve", "watch", "login", "clear", "echo", "cat", "jobs", "fg",
+    "kill", "history", "cd", "pwd", "pushd", "popd", "set", "export", "unset", "test", "source",
+    "alias", "unalias", "quit", "exit",
+];
+
+/// Variables that are always set.
+pub const SPECIAL_VARIABLES: &[&str] = &["s", "selected", "user", "server", "team", "?"];
+
+/// Value of `$name`. Variables given for a single command line take the place of any set with
+/// `set`, which take the place of fj-tui's own environment variables.
+fn variable(name: &str, ctx: &ShellContext, env: &Env) -> String {
+    match name {
+        "s" | "selected" => ctx.selected.clone().unwrap_or_default(),
+        "user" => ctx.session.creds.username.clone(),
+        "server" => ctx.session.server.to_string(),
+        "team" => ctx.team.clone().unwrap_or_default(),
+        "?" => ctx.variables.lock().unwrap().status.to_string(),
+        _ => env
+            .get(name)
+            .cloned()
+            .or_else(|| ctx.variables.lock().unwrap().get(name).map(String::from))
+            .or_else(|| std::env::var(name).ok())
+            .unwrap_or_default(),
+    }
+}
+
+async fn run_command(
+    args: Vec<String>,
+    ctx: &ShellContext,
+    mut output: Output,
+    piped_input: Option<Input>,
+    env: &Env,
+) -> Output {
+    let mut args = args.into_iter();
+    let Some(command) = args.next() else {
+        return output;
+    };
+    let args = args.collect::<Vec<String>>();
+
     match command.as_str() {
         "h" | "help" => {
-            let mut app_state = app_state.lock().await;
-            app_state.console.println("Commands:");
-            app_state.console.println("  help");
-            app_state.console.println("  fuzz <slug>");
-            app_state
-                .console
-                .println("  judge <slug> <solution> <source-path>");
-            app_state.console.println("  clear");
-            app_state.console.println("  echo <message>");
-            app_state.console.println("  cat <file-path>");
-            app_state.console.println("  quit");
+            output.println("Commands:").await;
+            output.println("  help").await;
+            output.println("  fuzz <slug>").await;
+            output.println("  judge <slug> <source-path>").await;
+            output
+                .println("  solve [-y] [-t <seconds>] <slug> <source-path> -- <command>")
+                .await;
+            output
+                .println("  watch [-a] [-p <slug>] <path> [-- <command>]")
+                .await;
+            output.println("  login").await;
+            output.println("  clear").await;
+            output.println("  echo <message>").await;
+            output.println("  cat <file-path>").await;
+            output.println("  jobs").await;
+            output.println("  fg [%job]").await;
+            output
+                .println("  kill %job, or kill <pid> for the system's kill")
+                .await;
+            output.println("  history").await;
+            output
+                .println("  cd [<dir>], pwd, pushd [<dir>], popd")
+                .await;
+            output
+                .println("  set [<name> <value>], export [<name>[=<value>]], unset <name>")
+                .await;
+            output
+                .println("  test <expression>, [ <expression> ]")
+                .await;
+            output.println("  source <file>").await;
+            output
+                .println("  alias [<name>[=<value>]], unalias <name>...")
+                .await;
+            output
+                .println("  !n (run command n from history again)")
+                .await;
+            output.println("  quit [<status>]").await;
         }
         "f" | "fuzz" => {
             if args.len() != 1 {
-                app_state
-                    .lock()
-                    .await
-                    .console
-                    .eprintln("Usage: fuzz <slug>");
+                output.eprintln("Usage: fuzz <slug>").await;
                 output.status = 1;
                 return output;
             }
 
-            app_state.lock().await.console.println("Request sent...");
-
-            let slug = args[0].to_string();
-
-            let response = app_state.lock().await.session.fuzz(slug).await;
+            ctx.info("Request sent...");
 
-            let out = match response {
-                Ok(response) => response,
-                Err(e) => Some(e.to_string()),
-            };
-
-            output.println(out.as_deref().unwrap_or("")).await;
+            match ctx.session.fuzz(&args[0]).await {
+                Ok(input) => output.println(&input).await,
+                Err(e) => {
+                    ctx.report(&e);
+                    output.status = 1;
+                }
+            }
         }
         "j" | "judge" => {
             if args.len() != 2 {
-                app_state
-                    .lock()
-                    .await
-                    .console
-                    .eprintln("Usage: judge <slug> <source-path>");
+                output.eprintln("Usage: judge <slug> <source-path>").await;
                 output.status = 1;
                 return output;
             }
 
-            let solution = if let Some(input) = piped_input {
-                input
+            let solution = if let Some(mut input) = piped_input {
+                let mut solution = String::new();
+                if let Err(e) = input.read_to_string(&mut solution).await {
+                    output.eprintln(&format!("judge: {}", e)).await;
+                    output.status = 1;
+                    return output;
+                }
+                solution
             } else {
-                app_state
-                    .lock()
-                    .await
-                    .console
-                    .eprintln("No solution was provided. Please pipe the solution into this command. e.g. `echo \"solution\" | judge <slug> <source-path>`");
+                output.eprintln("No solution was provided. Please pipe the solution into this command. e.g. `echo \"solution\" | judge <slug> <source-path>`").await;
                 output.status = 1;
                 return output;
             };
 
-            let slug = args[0].to_string();
-            let source_path = PathBuf::from(&args[1]);
-
-            app_state.lock().await.console.println("Request sent...");
-
-            let response = app_state
-                .lock()
-                .await
-                .session
-                .judge(slug, solution, source_path)
-                .await;
-
-            let out = match response {
-                Ok(response) => response,
-                Err(e) => e.to_string(),
+            let source = match tokio::fs::read_to_string(ctx.path(&args[1])).await {
+                Ok(source) => source,
+                Err(e) => {
+                    output.eprintln(&format!("{}: {}", args[1], e)).await;
+                    output.status = 1;
+                    return output;
+                }
             };
 
-            output.println(&out).await;
+            return submit(&args[0], &solution, &source, ctx, output).await;
+        }
+        "solve" => return solve::solve(&args, ctx, output).await,
+        "watch" => return watch::watch(&args, ctx, output).await,
+        "login" => {
+            ctx.info("Logging in...");
+
+            match ctx.session.login().await {
+                Ok(_) => {
+                    output
+                        .println(&format!("Logged in as {}.", ctx.session.creds.username))
+                        .await
+                }
+                Err(Error::Auth(message)) => {
+                    output.eprintln(&format!("Login failed: {}", message)).await;
+                    output.status = 1;
+                }
+                Err(e) => {
+                    ctx.report(&e);
+                    output.status = 1;
+                }
+            }
         }
         "clear" => {
-            app_state.lock().await.console.clear();
+            ctx.send(Event::ClearConsole);
         }
         "echo" => {
             output.println(&args.join(" ")).await;
         }
         "cat" => {
             if args.len() != 1 {
-                app_state
-                    .lock()
-                    .await
-                    .console
-                    .eprintln("Usage: cat <file-path>");
+                output.eprintln("Usage: cat <file-path>").await;
                 output.status = 1;
                 return output;
             }
 
-            let path = PathBuf::from(&args[0]);
-
-            let res = tokio::fs::read_to_string(path).await;
-
-            let out = match res {
-                Ok(content) => content,
+            match tokio::fs::File::open(ctx.path(&args[0])).await {
+                Ok(mut file) => output.print_all(&mut file).await,
                 Err(e) => {
+                    output.eprintln(&format!("{}: {}", args[0], e)).await;
                     output.status = 1;
-                    e.to_string()
                 }
+            }
+        }
+        "jobs" => {
+            for job in &ctx.jobs {
+                output
+                    .println(&format!("[{}]  Running  {}", job.id, job.command))
+                    .await;
+            }
+        }
+        "fg" => {
+            let job = match args.first() {
+                Some(arg) => find_job(ctx, arg),
+                None => ctx.jobs.iter().rev().find(|job| job.background),
             };
 
-            output.println(&out).await;
+            match job {
+                Some(job) => {
+                    output.println(&job.command).await;
+                    ctx.send(Event::Foreground(job.id));
+                }
+                None => {
+                    output.eprintln("fg: no such job").await;
+                    output.status = 1;
+                }
+            }
         }
-        "q" | "quit" | "exit" => {
-            app_state.lock().await.running = false;
-        }
-        _ => {
-            let cmd = Command::new(command)
-                .args(args)
-                .stdin(std::process::Stdio::piped())
-                .stdout(std::process::Stdio::piped())
-                .stderr(std::process::Stdio::piped())
-                .spawn();
-
-            if let Err(e) = cmd {
-                output.println(&e.to_string()).await;
-                output.status = 1;
-                return output;
+        // Anything but a job is for the system's `kill`, such as a process ID.
+        "kill" if args.first().is_some_and(|arg| arg.starts_with('%')) => {
+            for arg in &args {
+                match find_job(ctx, arg) {
+                    Some(job) => ctx.send(Event::Kill(job.id)),
+                    None => {
+                        output
+                            .eprintln(&format!("kill: {}: no such job", arg))
+                            .await;
+                        output.status = 1;
+                    }
+                }
             }
-
-            let mut child = cmd.unwrap();
-            let stdin = child.stdin.as_mut().unwrap();
-
-            if let Some(input) = piped_input {
-                stdin.write_all(input.as_bytes()).await.unwrap();
+        }
+        "history" => {
+            for (i, command) in ctx.history.iter().enumerate() {
+                output.println(&format!("{:>5}  {}", i + 1, command)).await;
             }
-
-            let cmd_output = child.wait_with_output().await;
-
-            output.status = cmd_output.as_ref().unwrap().status.code().unwrap_or(1);
-
-            let out = match cmd_output {
-                Ok(output) => String::from_utf8_lossy(&output.stdout).to_string(),
-                Err(e) => e.to_string(),
-            };
-
-            output.println(&out).await;
         }
-    }
+        "test" | "[" => {
+            let mut args = args;
+            if command == "[" && args.pop().as_deref() != Some("]") {
+                output.eprintln("[: missing `]'").await;
+                output.status = 2;
+                return output;
+            }
 
-    output
-}
-fn tokenize(command: &str) -> Vec<String> {
-    let mut tokens = vec![];
-
-    let mut in_quote = false;
-    let mut current = String::new();
-    let mut escaped = false;
-    let mut bracket_depth = 0;
-
-    for c in command.chars() {
-        if c.is_whitespace() && !in_quote && bracket_depth == 0 {
-            if !current.is_empty() {
-                tokens.push(current.clone());
-                current.clear();
+            match condition::test(&args, &ctx.cwd()) {
+                Ok(true) => {}
+                Ok(false) => output.status = 1,
+                Err(e) => {
+                    output.eprintln(&format!("{}: {}", command, e)).await;
+                    output.status = 2;
+                }
             }
-            escaped = false;
-            continue;
-        } else if !escaped && c == '"' {
-            in_quote = !in_quote;
-            escaped = false;
-            continue;
-        } else if !escaped && c == '(' {
-            bracket_depth += 1;
-        } else if !escaped && c == ')' {
-            bracket_depth -= 1;
-        } else if !escaped && c == '\\' {
-            escaped = true;
-            continue;
         }
+        "source" | "." => {
+            let Some(path) = args.first() else {
+                output.eprintln("Usage: source <file>").await;
+                output.status = 1;
+                return output;
+            };
 
-        current.push(c);
-        escaped = false;
-    }
-
-    if !current.is_empty() {
-        tokens.push(current.clone());
-    }
-
-    tokens
-}
-
-fn split_by_oper(command: &str, oper: &str, last: bool) -> Option<(String, String)> {
-    let words = command.split_whitespace().filter(|s| !s.is_empty());
-
-    let mut oper_pos = None;
-
-    if last {
-        for (i, word) in words.clone().enumerate() {
-            if word == oper {
-                oper_pos = Some(i);
+            let list = match tokio::fs::read_to_string(ctx.path(path)).await {
+                Ok(script) => parser::parse_line(&script).map_err(|e| format!("{}: {}", path, e)),
+                Err(e) => Err(format!("{}: {}", path, e)),
+            };
+            match list {
+                Ok(list) => {
+                    output.status = eval_body(&list, ctx, piped_input, &mut output, env).await;
+                }
+                Err(e) => {
+                    output.eprintln(&e).await;
+                    output.status = 1;
+                }
             }
         }
-    } else {
-        for (i, word) in words.clone().enumerate() {
-            if word == oper {
-                oper_pos = Some(i);
-                break;
-            }
+        "alias" => return definitions::alias(&args, ctx, output).await,
+        "unalias" => return definitions::unalias(&args, ctx, output).await,
+        "cd" => return directories::cd(&args, ctx, output).await,
+        "pwd" => return directories::pwd(ctx, output).await,
+        "pushd" => return directories::pushd(&args, ctx, output).await,
+        "popd" => return directories::popd(ctx, output).await,
+        "set" => return variables::set(&args, ctx, output).await,
+        "export" => return variables::export(&args, ctx, output).await,
+        "unset" => return variables::unset(&args, ctx, output).await,
+        "q" | "quit" | "exit" => {
+            // Like other shells, exit with the last command's status unless given one.
+            output.status = match args.first() {
+                Some(status) => match status.parse() {
+                    Ok(status) => status,
+                    Err(_) => {
+                        output.eprintln("Usage: quit [<status>]").await;
+                        output.status = 2;
+                        return output;
+                    }
+                },
+                None => ctx.variables.lock().unwrap().status,
+            };
+            ctx.send(Event::Quit);
         }
+        _ => return run_external(&command, args, ctx, output, piped_input).await,
     }
 
-    if let Some(pos) = oper_pos {
-        let first = words.clone().take(pos).collect::<Vec<&str>>().join(" ");
-        let second = words.clone().skip(pos + 1).collect::<Vec<&str>>().join(" ");
-        return Some((first, second));
-    }
-
-    None
-}
-
-#[derive(Clone, Copy, PartialEq, Eq, Default)]
-pub enum OutputMode {
-    #[default]
-    ToConsole,
-    Piped,
+    output
 }
 
-#[derive(Clone)]
-pub struct Output {
-    status: i32,
-    stdout: String,
-    #[allow(dead_code)]
-    stderr: String,
-    mode: OutputMode,
-    app_state: Arc<tokio::sync::Mutex<AppState>>,
-}
+/// Sends a solution to be judged, showing the verdict and any errors.
+async fn submit(
+    slug: &str,
+    solution: &str,
+    source: &str,
+    ctx: &ShellContext,
+    mut output: Output,
+) -> Output {
+    ctx.info("Request sent...");
 
-impl Output {
-    pub fn new(mode: OutputMode, app_state: Arc<tokio::sync::Mutex<AppState>>) -> Self {
-        Self {
-            mode,
-            app_state,
-            status: 0,
-            stdout: String::new(),
-            stderr: String::new(),
+    match ctx.session.judge(slug, solution, source).await {
+        Ok(message) => {
+            output.println(&message).await;
+            ctx.send(Event::Judged(slug.to_string()));
         }
-    }
-}
-
-impl Output {
-    pub async fn println(&mut self, s: &str) {
-        match self.mode {
-            OutputMode::Piped => {
-                self.stdout.push_str(s);
-                self.stdout.push('\n');
-            }
-            OutputMode::ToConsole => {
-                self.app_state.lock().await.console.println(s);
+        Err(Error::Rejected { message, errors }) => {
+            output.eprintln(&message).await;
+            for line in errors.lines() {
+                output.eprintln(line).await;
             }
+            output.status = 1;
+            ctx.send(Event::Judged(slug.to_string()));
+        }
+        Err(e) => {
+            ctx.report(&e);
+            output.status = 1;
         }
     }
-}
-
-async fn write_file(
-    app_state: Arc<tokio::sync::Mutex<AppState>>,
-    (path, content): (PathBuf, String),
-) {
-    let res = tokio::fs::write(path, content).await;
-    if let Err(e) = res {
-        app_state.lock().await.console.eprintln(&e.to_string());
-    }
-}
 
-fn is_var_name_char(c: char) -> bool {
-    c.is_alphanumeric() || c == '_'
+    output
 }
 
-fn split_out_variables(arg: &str) -> Vec<String> {
-    let mut parts = vec![];
-    let mut current = String::new();
-    let mut in_var = false;
-    let mut bracket_depth = 0;
-
-    let push = |current: &mut String, parts: &mut Vec<String>| {
-        if !current.is_empty() {
-            parts.push(current.clone());
-            current.clear();
-        }
+/// Runs a program, printing its output as it comes.
+async fn run_external(
+    command: &str,
+    args: Vec<String>,
+    ctx: &ShellContext,
+    mut output: Output,
+    piped_input: Option<Input>,
+) -> Output {
+    let stdin = if piped_input.is_some() {
+        Stdio::piped()
+    } else {
+        Stdio::null()
     };
 
-    for c in arg.chars() {
-        if c == '(' {
-            bracket_depth += 1;
-        } else if c == ')' {
-            bracket_depth -= 1;
-            if bracket_depth == 0 {
-                current.push(c);
-                push(&mut current, &mut parts);
-                continue;
+    // A program given by its path is found from the working directory too.
+    let cwd = ctx.cwd();
+    let mut cmd = if command.contains('/') {
+        process::Command::new(cwd.join(command))
+    } else {
+        process::Command::new(command)
+    };
+    let exported = ctx.variables.lock().unwrap().exported();
+    cmd.args(args)
+        .envs(exported)
+        .env("PWD", &cwd)
+        .current_dir(&cwd)
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    // Its own group, so killing it also kills anything it starts.
+    #[cfg(unix)]
+    cmd.process_group(0);
+    let cmd = cmd.spawn();
+
+    let mut child = match cmd {
+        Ok(child) => child,
+        Err(e) => {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                output
+                    .eprintln(&format!("{}: command not found", command))
+                    .await;
+            } else {
+                output.eprintln(&format!("{}: {}", command, e)).await;
             }
-        } else if bracket_depth == 0 && c == '$' {
-            push(&mut current, &mut parts);
-            in_var = true;
-        } else if bracket_depth == 0 && in_var && !is_var_name_char(c) {
-            push(&mut current, &mut parts);
-            in_var = false;
+            output.status = 1;
+            return output;
         }
-        current.push(c);
-    }
-
-    push(&mut current, &mut parts);
+    };
 
-    parts
-}
+    let mut group = ProcessGroup(child.id());
 
-async fn handle_variable(
-    part: &str,
-    app_state: Arc<tokio::sync::Mutex<AppState>>,
-    env: &Env,
-) -> String {
-    assert!(part.starts_with('$'));
+    if let (Some(mut input), Some(mut stdin)) = (piped_input, child.stdin.take()) {
+        // Fed on its own so a command that stops reading can't hold up its output.
+        tokio::spawn(async move {
+            let _ = tokio::io::copy(&mut input, &mut stdin).await;
+        });
+    }
 
-    let var = part.trim_start_matches('$');
+    let (chunks, mut receiver) = mpsc::channel(OUTPUT_BUFFER);
+    let stdout_task = child
+        .stdout
+        .take()
+        .map(|stdout| tokio::spawn(forward(stdout, Stream::Stdout, chunks.clone())));
+    if let Some(stderr) = child.stderr.take() {
+        tokio::spawn(forward(stderr, Stream::Stderr, chunks));
+    }
 
-    if var.is_empty() {
-        return "$".to_string();
+    while let Some((stream, chunk)) = receiver.recv().await {
+        output.write_bytes(stream, &chunk).await;
+        // Closing its output tells the command to stop, as it would in other shells.
+        if stream == Stream::Stdout && output.is_broken() {
+            stdout_task.iter().for_each(|task| task.abort());
+        }
     }
+    output.end_bytes().await;
 
-    if var.starts_with('(') && var.ends_with(')') {
-        let mut command = var.to_string();
-        command.remove(0);
-        command.pop();
+    let status = child.wait().await;
+    group.0 = None;
 
-        let output = exec(&command, app_state.clone(), OutputMode::Piped, None, env).await;
+    output.status = match status {
+        Ok(status) => status.code().unwrap_or(1),
+        Err(e) => {
+            output.eprintln(&format!("{}: {}", command, e)).await;
+            1
+        }
+    };
 
-        return output.stdout;
-    }
+    output
+}
 
-    match var {
-        "s" | "selected" => {
-            let selected = app_state
-                .lock()
-                .await
-                .selected_problem_borrow()
-                .clone()
-                .selected()
-                .unwrap_or(0);
+/// A job given as `%1`.
+fn find_job<'a>(ctx: &'a ShellContext, arg: &str) -> Option<&'a Job> {
+    let id = arg.strip_prefix('%')?.parse::<usize>().ok()?;
+    ctx.jobs.iter().find(|job| job.id == id)
+}
 
-            if let Some(selected) = app_state.lock().await.problems.get(selected) {
-                return selected.slug.clone();
-            }
-        }
-        _ => {
-            if let Some(value) = env.get(var) {
-                return value.clone();
+/// Kills a command's process group if it's dropped before the command finishes, as when its job
+/// is killed, so anything the command started goes with it.
+struct ProcessGroup(Option<u32>);
+
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(pid) = self.0 {
+            // SAFETY: Only sends a signal. The group can't have been reused, as the command
+            // hasn't been waited on.
+            unsafe {
+                libc::kill(-(pid as i32), libc::SIGKILL);
             }
         }
     }
-
-    "".to_string()
 }
 
-async fn process_arg(arg: &str, app_state: Arc<tokio::sync::Mutex<AppState>>, env: &Env) -> String {
-    let parts = split_out_variables(arg);
-    let mut new_arg = String::new();
-
-    for part in parts {
-        if part.starts_with('$') {
-            new_arg.push_str(&handle_variable(&part, app_state.clone(), env).await);
-        } else {
-            new_arg.push_str(&part);
+/// Sends everything read to `chunks`, so both of a command's outputs can be printed as they come.
+async fn forward(
+    mut reader: impl AsyncRead + Unpin,
+    stream: Stream,
+    chunks: mpsc::Sender<(Stream, Vec<u8>)>,
+) {
+    let mut buffer = vec![0; 8 * 1024];
+    while let Ok(n @ 1..) = reader.read(&mut buffer).await {
+        if chunks.send((stream, buffer[..n].to_vec())).await.is_err() {
+            return;
         }
     }
-
-    new_arg
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::api;
+    use tokio::sync::mpsc;
+
+    use crate::console::ConsoleState;
 
     use super::*;
 
     #[tokio::test]
     async fn test_shell() {
-        let app_state = AppState::new(api::Session::new_no_connection());
-        let app_state = Arc::new(tokio::sync::Mutex::new(app_state));
+        let (events, mut receiver) = mpsc::unbounded_channel();
+        let ctx = ShellContext {
+            session: Arc::new(Session::new_no_connection()),
+            events,
+            selected: None,
+            jobs: vec![],
+            history: vec![],
+            variables: SharedVariables::default(),
+            team: None,
+            definitions: SharedDefinitions::default(),
+            depth: 0,
+            directories: SharedDirectories::default(),
+            auto_submissions: SharedAutoSubmissions::default(),
+        };
         let mut env = Env::new();
         env.insert("a".to_string(), "Some Value".to_string());
+        let tilde = format!("{}/x ~x ~", std::env::var("HOME").unwrap());
 
-        // TODO: Support all of these
         let input_output_compare_tests = vec![
             ("echo hello", Some("hello"), 0),
             ("echo hello world", Some("hello world"), 0),
@@ -503,8 +1152,14 @@ mod tests {
             ("echo hello && echo hello", Some("hello\nhello"), 0),
             ("cat non-existent-file", None, 1),
             ("ahjsdkjashldkjashdashdkj", None, 1),
-            ("echo $(echo $(echo hi) hi) hello)", Some("hi hi hello"), 0),
-            ("echo 2>1", Some(""), 0),
+            ("echo $(echo $(echo hi) hi) hello", Some("hi hi hello"), 0),
+            ("echo )", Some("ERROR: Syntax error: unexpected `)`"), 2),
+            (")", Some("ERROR: Syntax error: unexpected `)`"), 2),
+            ("echo a; ; echo b", Some("ERROR: Syntax error: unexpected `;`"), 2),
+            ("echo a &; echo b", Some("ERROR: Syntax error: unexpected `;`"), 2),
+            ("; echo a", Some("ERROR: Syntax error: unexpected `;`"), 2),
+            ("echo a;\n\necho b;", Some("a\nb"), 0),
+            ("echo 2 >1", Some(""), 0),
             ("cat 1", Some("2"), 0),
             ("rm 1", Some(""), 0),
             ("echo \"a\\na\"", Some("a\na"), 0),
@@ -515,22 +1170,225 @@ mod tests {
                 Some("hi  hi hello"),
                 0,
             ),
+            ("echo a&&echo b", Some("a\nb"), 0),
+            ("echo \"a ; b\"", Some("a ; b"), 0),
+            (
+                "cat || echo b && echo c",
+                Some("ERROR: Usage: cat <file-path>\nb\nc"),
+                0,
+            ),
+            ("echo $(echo a; echo b) '$a'", Some("a b $a"), 0),
+            ("echo x>2 ; cat 2 && rm 2", Some("x"), 0),
+            (
+                "cat nofile 2> err; cat err && rm err",
+                Some("nofile: No such file or directory (os error 2)"),
+                0,
+            ),
+            ("echo b > f; echo a >> f; sort < f && rm f", Some("a\nb"), 0),
+            ("sh -c 'echo oops >&2'", Some("oops"), 0),
+            ("sh -c 'echo oops >&2' 2>&1 | tr a-z A-Z", Some("OOPS"), 0),
+            ("echo hi 1>&2 2>/dev/null", Some("hi"), 0),
+            (
+                "sh -c 'echo a; echo b >&2' &> f; cat f && rm f",
+                Some("a\nb"),
+                0,
+            ),
+            (
+                "cat < nofile",
+                Some("ERROR: nofile: No such file or directory (os error 2)"),
+                1,
+            ),
+            ("seq 1 100000 | tail -n 1", Some("100000"), 0),
+            ("yes | head -n 2", Some("y\ny"), 0),
+            ("seq 3 > f; cat f | sort -r && rm f", Some("3\n2\n1"), 0),
+            ("set b one  two; echo $b", Some("one two"), 0),
+            ("set a other; echo $a", Some("Some Value"), 0),
+            ("sh -c 'echo \"[$b]\"'", Some("[]"), 0),
+            ("export b c=3; sh -c 'echo $b $c'", Some("one two 3"), 0),
+            ("unset b; echo \"[$b]\"; export", Some("[]\nexport c=3"), 0),
+            ("set 1x y", Some("ERROR: set: `1x': not a valid name"), 1),
+            ("unset s", Some("ERROR: unset: s: read-only variable"), 1),
+            ("false; echo $? || true; echo $?", Some("1\n0"), 0),
+            ("false || echo b", Some("b"), 0),
+            (
+                "if [ a = b ]; then echo 1; elif test 2 -gt 1; then echo 2; else echo 3; fi",
+                Some("2"),
+                0,
+            ),
+            ("if false; then echo 1; fi", Some(""), 0),
+            (
+                "if [ a = a; then echo 1; fi",
+                Some("ERROR: [: missing `]'"),
+                0,
+            ),
+            (
+                "for q in x 'y z'; do echo \"<$q>\"; done",
+                Some("<x>\n<y z>"),
+                0,
+            ),
+            (
+                "for q in 3 1 2; do echo $q; done | sort",
+                Some("1\n2\n3"),
+                0,
+            ),
+            ("echo $(for q in a b; do echo $q; done)", Some("a b"), 0),
+            (
+                "for q in a b; do echo $q; done > f; cat f && rm f",
+                Some("a\nb"),
+                0,
+            ),
+            ("for q in; do echo $q; done", Some(""), 0),
+            (
+                "set n 0; while [ $n -lt 3 ]; do set n $(echo $n | tr 012 123); echo $n; done",
+                Some("1\n2\n3"),
+                0,
+            ),
+            ("while false; do echo x; done; echo $?", Some("0"), 0),
+            ("{ echo b; echo a; } | sort", Some("a\nb"), 0),
+            (
+                "greet() { echo \"hi $1 ($#)\"; }; greet you there",
+                Some("hi you (2)"),
+                0,
+            ),
+            ("function up { tr a-z A-Z; }; echo abc | up", Some("ABC"), 0),
+            (
+                "loop() { loop; }; loop",
+                Some("ERROR: loop: too many nested function calls"),
+                1,
+            ),
+            ("alias ll='echo ll:'; ll a b", Some("ll: a b"), 0),
+            (
+                "alias echo='echo e:' two='echo x | ll'; two y",
+                Some("e: ll: y"),
+                0,
+            ),
+            ("alias two", Some("alias two='echo x | ll'"), 0),
+            ("unalias echo two; alias", Some("alias ll='echo ll:'"), 0),
+            (
+                "alias bad='echo |'",
+                Some("ERROR: alias: bad: expected a command"),
+                1,
+            ),
+            (
+                "echo 'f() { echo \"[$@]\"; }' > rc; source rc; f 1 2 && rm rc",
+                Some("[1 2]"),
+                0,
+            ),
+            ("echo Cargo.*", Some("Cargo.lock Cargo.toml"), 0),
+            (
+                "echo \"Cargo.*\" Cargo.\\* 'C'*.toml",
+                Some("Cargo.* Cargo.* Cargo.toml"),
+                0,
+            ),
+            ("echo nothing*here", Some("nothing*here"), 0),
+            (
+                "set p 'Cargo.t*'; echo $p \"$p\"",
+                Some("Cargo.toml Cargo.t*"),
+                0,
+            ),
+            ("echo src/{main,rc}.rs", Some("src/main.rs src/rc.rs"), 0),
+            (
+                "echo {a,b{1,2}}x \"{a,b}\" {} {a}",
+                Some("ax b1x b2x {a,b} {} {a}"),
+                0,
+            ),
+            ("echo a{,\"b c\"}", Some("a ab c"), 0),
+            ("echo ~/x ~x \"~\"", Some(tilde.as_str()), 0),
+            (
+                "pushd src > /dev/null; echo shell/di*.rs; test -f main.rs && ls main.rs; popd > /dev/null",
+                Some("shell/directories.rs\nmain.rs"),
+                0,
+            ),
+            (
+                "cd src; echo x > x.tmp; basename $(pwd); cd ..; cat src/x.tmp; rm src/x.tmp",
+                Some("src\nx"),
+                0,
+            ),
+            ("cd nowhere", None, 1),
+            ("popd", Some("ERROR: popd: directory stack empty"), 1),
+            ("tr x X <<EOF\nx $a\n$(echo x)\nEOF", Some("X Some Value\nX"), 0),
+            ("tr x X <<'EOF' && echo y\nx $a\\\nEOF", Some("X $a\\\ny"), 0),
+            ("tr x X <<-EOF; echo y\n\tx\n\tEOF", Some("X\ny"), 0),
+            (
+                "echo <<EOF\nx",
+                Some("ERROR: Syntax error: here-document ended before `EOF`"),
+                2,
+            ),
+            ("{ cat nofile; } 2>/dev/null", Some(""), 1),
+            ("if cat nofile; then echo; fi 2>/dev/null", Some(""), 0),
+            ("for f in a; do cat nofile; done 2>/dev/null", Some(""), 1),
+            ("{ yes; } | head -n 1", Some("y"), 0),
+            ("while true; do echo y; done | head -n 2", Some("y\ny"), 0),
+            ("f() { yes; }; f | head -n 1", Some("y"), 0),
+            ("f() { cat nofile; }; f 2>/dev/null", Some(""), 1),
+            (
+                "cd src; echo $(cd shell; basename $(pwd)); cd .. | tr a b; basename $(pwd); cd ..",
+                Some("shell\nsrc"),
+                0,
+            ),
+            (
+                "set x 1; echo $(set x 2; echo $x); echo $x",
+                Some("2\n1"),
+                0,
+            ),
+            ("echo $(false) $?; set y $(false); echo $?", Some("1\n1"), 0),
+            ("echo $HOME", Some(tilde.trim_end_matches("/x ~x ~")), 0),
+            ("kill %9", Some("ERROR: kill: %9: no such job"), 1),
+            ("kill -l 9", Some("KILL"), 0),
+            ("{ cat <missing; } 2>/dev/null", Some(""), 1),
+            (
+                "touch a.tmp b.tmp; echo x > *.tmp; ls *.tmp; rm *.tmp",
+                Some("ERROR: a.tmp b.tmp: ambiguous redirect\na.tmp\nb.tmp"),
+                0,
+            ),
+            ("echo x > $nothing", Some("ERROR: ambiguous redirect"), 1),
+            (
+                "mkdir g.tmp; cd g.tmp; pushd .. >/dev/null; rmdir g.tmp; popd 2>/dev/null; popd 2>&1 | grep -c g.tmp",
+                Some("1"),
+                0,
+            ),
+            ("test \\( a = a \\) -a \\( x = y -o 1 \\)", Some(""), 0),
+            ("[ \\( a = b \\) ]", Some(""), 1),
+            ("printf abc | wc -c", Some("3"), 0),
+            ("printf 'a\\nb' | wc -l", Some("1"), 0),
+            ("printf abc >f; cat f | wc -c; rm f", Some("3"), 0),
+            (
+                "head -c 100000 /dev/urandom | gzip -c | gunzip | wc -c",
+                Some("100000"),
+                0,
+            ),
+            (
+                "echo \"a | b",
+                Some("ERROR: Syntax error: unclosed `\"`"),
+                2,
+            ),
         ];
 
         for (input, output, status) in input_output_compare_tests {
-            let cmd_output =
-                exec(input, app_state.clone(), OutputMode::ToConsole, None, &env).await;
+            let cmd_output = exec(input, &ctx, OutputMode::ToConsole, &env).await;
             println!("testing: {}", input);
 
             assert_eq!(cmd_output.status, status);
 
-            let console_output = app_state.lock().await.console.messages.join("\n");
+            let mut console = ConsoleState::default();
+            while let Ok(event) = receiver.try_recv() {
+                match event {
+                    Event::Print(message) => console.println(&message),
+                    Event::EPrint(message) => console.eprintln(&message),
+                    Event::Stderr(message) => console.print_stderr(&message),
+                    _ => {}
+                }
+            }
+            let console_output = console
+                .messages
+                .iter()
+                .map(|m| m.text.as_str())
+                .collect::<Vec<&str>>()
+                .join("\n");
 
             if let Some(output) = output {
                 assert_eq!(console_output, output);
             }
-
-            app_state.lock().await.console.messages.clear();
         }
     }
 }
@@ -0,0 +1,133 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Syntax tree of a command line.
+
+/// Commands separated by `;`, newlines or `&`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct List(pub Vec<ListItem>);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListItem {
+    pub and_or: AndOr,
+    /// Ended with `&`, so runs alongside the rest of the list.
+    pub background: bool,
+}
+
+/// Pipelines joined by `&&` and `||`, run left to right.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AndOr {
+    pub first: Pipeline,
+    pub rest: Vec<(Connector, Pipeline)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connector {
+    /// `&&`: run if the previous pipeline succeeded.
+    And,
+    /// `||`: run if the previous pipeline failed.
+    Or,
+}
+
+/// Commands joined by `|`, each reading the output of the one before.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pipeline(pub Vec<Command>);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Simple(SimpleCommand),
+    /// A conditional, loop or group, with redirects that apply to everything inside it.
+    Compound(Compound, Vec<Redirect>),
+    /// `name() { ... }`, which defines a function when it's run.
+    Function {
+        name: String,
+        body: List,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimpleCommand {
+    pub words: Vec<Word>,
+    pub redirects: Vec<Redirect>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Compound {
+    /// `if ...; then ...; elif ...; then ...; else ...; fi`
+    If {
+        /// Each condition with the list run if it succeeds, tried in order.
+        branches: Vec<(List, List)>,
+        otherwise: Option<List>,
+    },
+    /// `for name in words; do ...; done`
+    For {
+        name: String,
+        words: Vec<Word>,
+        body: List,
+    },
+    /// `while ...; do ...; done`
+    While { condition: List, body: List },
+    /// `{ ...; }`
+    Group(List),
+}
+
+/// Points one of the command's streams at a file, or at another stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redirect {
+    /// 0 for stdin, 1 for stdout and 2 for stderr.
+    pub fd: u32,
+    pub kind: RedirectKind,
+    pub target: Word,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectKind {
+    /// `<`
+    Read,
+    /// `<<`: the target is the text to read.
+    HereDoc,
+    /// `>`
+    Write,
+    /// `>>`
+    Append,
+    /// `>&`: the target is the number of the stream to write to instead.
+    Duplicate,
+}
+
+/// One shell word, made of the pieces that get expanded and joined together.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Word(pub Vec<WordPart>);
+
+impl Word {
+    /// The text of a word that's a single piece of unquoted text, like a keyword.
+    pub fn literal(&self) -> Option<&str> {
+        match &self.0[..] {
+            [WordPart::Literal(text)] => Some(text),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WordPart {
+    /// Unquoted text.
+    Literal(String),
+    /// Text from quotes or a backslash escape, taken as is.
+    Quoted(String),
+    /// `$name` or `${name}`. Unquoted values are split into separate words on whitespace.
+    Variable { name: String, quoted: bool },
+    /// `$(...)`, replaced with the output of the commands. Split like variables.
+    Substitution { list: List, quoted: bool },
+}
@@ -0,0 +1,129 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Brace expansion, which turns `src/{main,lib}.rs` into `src/main.rs src/lib.rs`.
+
+use super::ast::{Word, WordPart};
+
+/// A piece of a word: an unquoted character, which might be a brace or comma, or any other part.
+#[derive(Clone)]
+enum Piece {
+    Char(char),
+    Part(WordPart),
+}
+
+/// Expands the first unquoted `{a,b,...}` in a word, then any in the words that gives. Braces
+/// without a comma between them are left alone, as are quoted ones.
+pub fn expand(word: &Word) -> Vec<Word> {
+    let pieces = word
+        .0
+        .iter()
+        .flat_map(|part| match part {
+            WordPart::Literal(text) => text.chars().map(Piece::Char).collect(),
+            part => vec![Piece::Part(part.clone())],
+        })
+        .collect::<Vec<_>>();
+
+    let mut words = vec![];
+    expand_pieces(pieces, &mut words);
+    words
+}
+
+fn expand_pieces(pieces: Vec<Piece>, words: &mut Vec<Word>) {
+    let Some((open, commas, close)) = find_braces(&pieces) else {
+        words.push(to_word(pieces));
+        return;
+    };
+
+    let starts = std::iter::once(open).chain(commas.iter().copied());
+    let ends = commas.iter().copied().chain(std::iter::once(close));
+    for (start, end) in starts.zip(ends) {
+        let mut expanded = pieces[..open].to_vec();
+        expanded.extend_from_slice(&pieces[start + 1..end]);
+        expanded.extend_from_slice(&pieces[close + 1..]);
+        expand_pieces(expanded, words);
+    }
+}
+
+/// Positions of the first opening brace that has a matching closing brace with a comma between
+/// them, the commas, and the closing brace.
+fn find_braces(pieces: &[Piece]) -> Option<(usize, Vec<usize>, usize)> {
+    for (open, piece) in pieces.iter().enumerate() {
+        if !matches!(piece, Piece::Char('{')) {
+            continue;
+        }
+        let Some(close) = matching_brace(pieces, open) else {
+            continue;
+        };
+        let commas = top_level_commas(pieces, open, close);
+        if !commas.is_empty() {
+            return Some((open, commas, close));
+        }
+    }
+    None
+}
+
+fn matching_brace(pieces: &[Piece], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, piece) in pieces.iter().enumerate().skip(open) {
+        match piece {
+            Piece::Char('{') => depth += 1,
+            Piece::Char('}') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn top_level_commas(pieces: &[Piece], open: usize, close: usize) -> Vec<usize> {
+    let mut depth = 0;
+    let mut commas = vec![];
+    for (i, piece) in pieces.iter().enumerate().take(close).skip(open + 1) {
+        match piece {
+            Piece::Char('{') => depth += 1,
+            Piece::Char('}') => depth -= 1,
+            Piece::Char(',') if depth == 0 => commas.push(i),
+            _ => {}
+        }
+    }
+    commas
+}
+
+fn to_word(pieces: Vec<Piece>) -> Word {
+    let mut parts = vec![];
+    let mut literal = String::new();
+
+    for piece in pieces {
+        match piece {
+            Piece::Char(c) => literal.push(c),
+            Piece::Part(part) => {
+                if !literal.is_empty() {
+                    parts.push(WordPart::Literal(std::mem::take(&mut literal)));
+                }
+                parts.push(part);
+            }
+        }
+    }
+    if !literal.is_empty() {
+        parts.push(WordPart::Literal(literal));
+    }
+
+    Word(parts)
+}
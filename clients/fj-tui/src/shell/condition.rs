@@ -0,0 +1,209 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! The `test` and `[` builtins.
+
+use std::path::Path;
+
+/// Evaluates the arguments of `test`, or of `[` without the closing `]`. Relative paths are
+/// checked from `cwd`.
+pub fn test(args: &[String], cwd: &Path) -> Result<bool, String> {
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    if args.is_empty() {
+        return Ok(false);
+    }
+
+    let mut parser = Parser { args: &args, cwd };
+    let result = parser.or()?;
+    match parser.args.first() {
+        None => Ok(result),
+        Some(arg) => Err(format!("{}: binary operator expected", arg)),
+    }
+}
+
+/// Reads an expression from the front of `args`. `-a` and `-o` only join complete primaries, so
+/// they can still be operands, as in `[ "$opt" = -a ]`.
+struct Parser<'a> {
+    args: &'a [&'a str],
+    cwd: &'a Path,
+}
+
+impl Parser<'_> {
+    fn or(&mut self) -> Result<bool, String> {
+        let mut result = self.and()?;
+        while self.connective("-o") {
+            result |= self.and()?;
+        }
+        Ok(result)
+    }
+
+    fn and(&mut self) -> Result<bool, String> {
+        let mut result = self.not()?;
+        while self.connective("-a") {
+            result &= self.not()?;
+        }
+        Ok(result)
+    }
+
+    fn not(&mut self) -> Result<bool, String> {
+        // A lone `!` is just a non-empty string, and so is one being compared.
+        if let ["!", next, ..] = self.args {
+            if self.args.len() >= 3 && is_binary(next) {
+                return self.primary();
+            }
+            self.args = &self.args[1..];
+            return self.not().map(|result| !result);
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<bool, String> {
+        let (result, used) = match self.args {
+            [left, op, right, ..] if is_binary(op) => (binary(left, op, right)?, 3),
+            ["(", _, ..] => {
+                self.args = &self.args[1..];
+                let result = self.or()?;
+                match self.args {
+                    [")", ..] => (result, 1),
+                    _ => return Err("`)` expected".to_string()),
+                }
+            }
+            [op, operand, ..] if is_unary(op) => (unary(op, operand, self.cwd)?, 2),
+            [op, _] if op.len() > 1 && op.starts_with('-') => {
+                return Err(format!("{}: unary operator expected", op))
+            }
+            [string, ..] => (!string.is_empty(), 1),
+            [] => return Err("argument expected".to_string()),
+        };
+        self.args = &self.args[used..];
+        Ok(result)
+    }
+
+    /// Consumes `connective` if it's next and joins on to another primary.
+    fn connective(&mut self, connective: &str) -> bool {
+        match self.args {
+            [arg, _, ..] if *arg == connective => {
+                self.args = &self.args[1..];
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn is_unary(op: &str) -> bool {
+    matches!(op, "-n" | "-z" | "-e" | "-f" | "-d" | "-s")
+}
+
+fn is_binary(op: &str) -> bool {
+    matches!(
+        op,
+        "=" | "==" | "!=" | "-eq" | "-ne" | "-lt" | "-le" | "-gt" | "-ge"
+    )
+}
+
+fn unary(op: &str, operand: &str, cwd: &Path) -> Result<bool, String> {
+    let path = cwd.join(operand);
+    Ok(match op {
+        "-n" => !operand.is_empty(),
+        "-z" => operand.is_empty(),
+        "-e" => path.exists(),
+        "-f" => path.is_file(),
+        "-d" => path.is_dir(),
+        "-s" => path.metadata().is_ok_and(|m| m.len() > 0),
+        _ => return Err(format!("{}: unary operator expected", op)),
+    })
+}
+
+fn binary(left: &str, op: &str, right: &str) -> Result<bool, String> {
+    let ordering = || -> Result<std::cmp::Ordering, String> {
+        let number = |s: &str| {
+            s.trim()
+                .parse::<i64>()
+                .map_err(|_| format!("{}: integer expression expected", s))
+        };
+        Ok(number(left)?.cmp(&number(right)?))
+    };
+
+    Ok(match op {
+        "=" | "==" => left == right,
+        "!=" => left != right,
+        "-eq" => ordering()?.is_eq(),
+        "-ne" => ordering()?.is_ne(),
+        "-lt" => ordering()?.is_lt(),
+        "-le" => ordering()?.is_le(),
+        "-gt" => ordering()?.is_gt(),
+        "-ge" => ordering()?.is_ge(),
+        _ => return Err(format!("{}: binary operator expected", op)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(expression: &str) -> Result<bool, String> {
+        let args = expression
+            .split_whitespace()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        test(&args, Path::new(env!("CARGO_MANIFEST_DIR")))
+    }
+
+    #[test]
+    fn test_test() {
+        assert_eq!(check(""), Ok(false));
+        assert_eq!(check("a"), Ok(true));
+        assert_eq!(check("-z a"), Ok(false));
+        assert_eq!(check("! -n a"), Ok(false));
+        assert_eq!(check("a = a"), Ok(true));
+        assert_eq!(check("a != a -o 1 -lt 2"), Ok(true));
+        assert_eq!(check("10 -gt 9 -a 3 -le 2"), Ok(false));
+        assert_eq!(check("-d /"), Ok(true));
+        assert_eq!(check("-f /"), Ok(false));
+        assert_eq!(check("-f src/main.rs"), Ok(true));
+        assert_eq!(
+            check("-x src/main.rs"),
+            Err("-x: unary operator expected".to_string())
+        );
+        assert_eq!(
+            check("a -lt 2"),
+            Err("a: integer expression expected".to_string())
+        );
+        assert_eq!(
+            check("a -x b"),
+            Err("-x: binary operator expected".to_string())
+        );
+
+        // `-a` and `-o` as operands rather than connectives.
+        assert_eq!(check("-a = -a"), Ok(true));
+        assert_eq!(check("x = -a"), Ok(false));
+        assert_eq!(check("-n -o"), Ok(true));
+        assert_eq!(check("-z -a"), Ok(false));
+        assert_eq!(check("-o"), Ok(true));
+        assert_eq!(check("! = !"), Ok(true));
+        assert_eq!(check("( a = b -o b = b ) -a -n x"), Ok(true));
+        assert_eq!(check("! ( a = a ) -o ( b )"), Ok(true));
+        assert_eq!(check("( = ("), Ok(true));
+        assert_eq!(check("( a"), Err("`)` expected".to_string()));
+        assert_eq!(check("! -o"), Ok(false));
+        assert_eq!(check("-n -a -o a = b"), Ok(true));
+        assert_eq!(check("x = -o -o -z -a"), Ok(false));
+        assert_eq!(
+            check("a -a"),
+            Err("-a: binary operator expected".to_string())
+        );
+    }
+}
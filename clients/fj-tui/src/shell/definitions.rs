@@ -0,0 +1,219 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Aliases and functions kept for the rest of the session, and the `alias` and `unalias`
+//! builtins.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use super::{
+    ast::{AndOr, Command, Compound, List, ListItem, Word, WordPart},
+    parser, quote, Output, ShellContext,
+};
+
+/// Shared by every command, so something defined by one can be used by the next.
+pub type SharedDefinitions = Arc<Mutex<Definitions>>;
+
+#[derive(Debug, Default)]
+pub struct Definitions {
+    aliases: BTreeMap<String, String>,
+    functions: BTreeMap<String, List>,
+}
+
+impl Definitions {
+    pub fn function(&self, name: &str) -> Option<&List> {
+        self.functions.get(name)
+    }
+
+    pub fn define_function(&mut self, name: &str, body: List) {
+        self.functions.insert(name.to_string(), body);
+    }
+
+    /// Replaces each command starting with an alias with what it stands for. Arguments after the
+    /// alias are added to the end, as if the alias had been typed out.
+    pub fn expand_aliases(&self, and_or: &AndOr) -> AndOr {
+        self.expand_and_or(and_or, &mut vec![])
+    }
+
+    fn expand_and_or(&self, and_or: &AndOr, seen: &mut Vec<String>) -> AndOr {
+        let mut and_or = and_or.clone();
+        let pipelines =
+            std::iter::once(&mut and_or.first).chain(and_or.rest.iter_mut().map(|(_, p)| p));
+
+        for pipeline in pipelines {
+            for command in &mut pipeline.0 {
+                self.expand_command(command, seen);
+            }
+        }
+
+        and_or
+    }
+
+    /// `seen` holds the aliases being expanded, so an alias can use a command of the same name.
+    fn expand_command(&self, command: &mut Command, seen: &mut Vec<String>) {
+        let Command::Simple(simple) = command else {
+            return;
+        };
+        let Some(name) = simple.words.first().and_then(|word| word.literal()) else {
+            return;
+        };
+        if seen.iter().any(|s| s == name) {
+            return;
+        }
+        let Some(Ok(mut list)) = self
+            .aliases
+            .get(name)
+            .map(|value| parser::parse_line(value))
+        else {
+            return;
+        };
+
+        seen.push(name.to_string());
+        let args = simple.words.split_off(1);
+        let redirects = std::mem::take(&mut simple.redirects);
+
+        // The arguments and redirects go on the last command of the alias.
+        let last = list
+            .0
+            .last_mut()
+            .map(|item| match item.and_or.rest.last_mut() {
+                Some((_, pipeline)) => pipeline,
+                None => &mut item.and_or.first,
+            })
+            .and_then(|pipeline| pipeline.0.last_mut());
+        match last {
+            Some(Command::Simple(last)) => {
+                last.words.extend(args);
+                last.redirects.extend(redirects);
+            }
+            Some(Command::Compound(_, last)) => last.extend(redirects),
+            _ => {}
+        }
+
+        for item in &mut list.0 {
+            item.and_or = self.expand_and_or(&item.and_or, seen);
+        }
+        seen.pop();
+
+        *command = match &list.0[..] {
+            [ListItem {
+                and_or: AndOr { first, rest },
+                background: false,
+            }] if rest.is_empty() && first.0.len() == 1 => first.0[0].clone(),
+            _ => {
+                // The group's commands are run through `expand_aliases` again, so quote their
+                // names to stop them being expanded twice, like `\echo` in other shells.
+                for item in &mut list.0 {
+                    let pipelines = std::iter::once(&mut item.and_or.first)
+                        .chain(item.and_or.rest.iter_mut().map(|(_, p)| p));
+                    for command in pipelines.flat_map(|pipeline| &mut pipeline.0) {
+                        if let Command::Simple(simple) = command {
+                            if let Some(name) = simple.words.first_mut() {
+                                if let Some(text) = name.literal() {
+                                    *name = Word(vec![WordPart::Quoted(text.to_string())]);
+                                }
+                            }
+                        }
+                    }
+                }
+                Command::Compound(Compound::Group(list), vec![])
+            }
+        };
+    }
+}
+
+/// `alias [<name>[=<value>]...]`: lists aliases, or defines or shows each one given.
+pub async fn alias(args: &[String], ctx: &ShellContext, mut output: Output) -> Output {
+    let show = |name: &str, value: &str| format!("alias {}={}", name, quote(value));
+
+    if args.is_empty() {
+        let lines = {
+            let definitions = ctx.definitions.lock().unwrap();
+            definitions
+                .aliases
+                .iter()
+                .map(|(name, value)| show(name, value))
+                .collect::<Vec<_>>()
+        };
+        for line in lines {
+            output.println(&line).await;
+        }
+        return output;
+    }
+
+    for arg in args {
+        let result = match arg.split_once('=') {
+            Some((name, _)) if !is_alias_name(name) => {
+                Err(format!("alias: `{}': not a valid name", name))
+            }
+            Some((name, value)) => match parser::parse_line(value) {
+                Ok(_) => {
+                    let mut definitions = ctx.definitions.lock().unwrap();
+                    definitions
+                        .aliases
+                        .insert(name.to_string(), value.to_string());
+                    Ok(None)
+                }
+                Err(e) => Err(format!("alias: {}: {}", name, e)),
+            },
+            None => match ctx.definitions.lock().unwrap().aliases.get(arg) {
+                Some(value) => Ok(Some(show(arg, value))),
+                None => Err(format!("alias: {}: not found", arg)),
+            },
+        };
+
+        match result {
+            Ok(Some(line)) => output.println(&line).await,
+            Ok(None) => {}
+            Err(e) => {
+                output.eprintln(&e).await;
+                output.status = 1;
+            }
+        }
+    }
+
+    output
+}
+
+/// `unalias <name>...`
+pub async fn unalias(args: &[String], ctx: &ShellContext, mut output: Output) -> Output {
+    if args.is_empty() {
+        output.eprintln("Usage: unalias <name>...").await;
+        output.status = 1;
+        return output;
+    }
+
+    for name in args {
+        let removed = ctx.definitions.lock().unwrap().aliases.remove(name);
+        if removed.is_none() {
+            output
+                .eprintln(&format!("unalias: {}: not found", name))
+                .await;
+            output.status = 1;
+        }
+    }
+
+    output
+}
+
+fn is_alias_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || "_-.".contains(c))
+}
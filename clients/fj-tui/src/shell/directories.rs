@@ -0,0 +1,217 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! The shell's working directory, and the `cd`, `pwd`, `pushd` and `popd` builtins.
+
+use std::{
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use super::{Output, ShellContext};
+
+/// Shared by every command, so `cd` in one changes where the next runs.
+pub type SharedDirectories = Arc<Mutex<Directories>>;
+
+#[derive(Debug, Clone)]
+pub struct Directories {
+    current: PathBuf,
+    /// Where `cd -` goes back to.
+    previous: Option<PathBuf>,
+    /// Directories saved by `pushd`, most recent last.
+    stack: Vec<PathBuf>,
+}
+
+impl Default for Directories {
+    /// Starts in the directory fj-tui was launched from.
+    fn default() -> Self {
+        Self::new(std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/")))
+    }
+}
+
+impl Directories {
+    pub fn new(current: PathBuf) -> Self {
+        Self {
+            current,
+            previous: None,
+            stack: vec![],
+        }
+    }
+
+    pub fn current(&self) -> &Path {
+        &self.current
+    }
+
+    /// Where a path typed in a command refers to.
+    pub fn resolve(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.current.join(path)
+    }
+
+    /// Changes to `path`, relative to the current directory, if it's a directory.
+    fn change(&mut self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        let target = normalize(&self.resolve(path));
+        match target.metadata() {
+            Ok(metadata) if metadata.is_dir() => {
+                self.previous = Some(std::mem::replace(&mut self.current, target));
+                Ok(())
+            }
+            Ok(_) => Err(format!("{}: Not a directory", path.display())),
+            Err(e) => Err(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    /// The current directory then the stack, as `pushd` and `popd` show them.
+    fn list(&self) -> String {
+        std::iter::once(&self.current)
+            .chain(self.stack.iter().rev())
+            .map(|path| display(path))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// A path with the home directory shortened to `~`.
+pub fn display(path: &Path) -> String {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    match home
+        .as_deref()
+        .and_then(|home| path.strip_prefix(home).ok())
+    {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.display().to_string(),
+    }
+}
+
+/// Removes `.` and `..` from a path without following links, so `cd ..` goes back the way it came.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// `cd [<dir>|-]`: changes to a directory, home if none is given, or the previous one for `-`.
+pub async fn cd(args: &[String], ctx: &ShellContext, mut output: Output) -> Output {
+    let result = match args {
+        [] => match std::env::var("HOME") {
+            Ok(home) => ctx.directories.lock().unwrap().change(&home),
+            Err(_) => Err("HOME not set".to_string()),
+        },
+        [dir] if dir == "-" => {
+            let result = {
+                let mut directories = ctx.directories.lock().unwrap();
+                match directories.previous.clone() {
+                    Some(previous) => directories
+                        .change(previous)
+                        .map(|()| display(directories.current())),
+                    None => Err("OLDPWD not set".to_string()),
+                }
+            };
+            match result {
+                Ok(current) => {
+                    output.println(&current).await;
+                    Ok(())
+                }
+                Err(e) => Err(e),
+            }
+        }
+        [dir] => ctx.directories.lock().unwrap().change(dir),
+        _ => Err("too many arguments".to_string()),
+    };
+
+    if let Err(e) = result {
+        output.eprintln(&format!("cd: {}", e)).await;
+        output.status = 1;
+    }
+    output
+}
+
+/// `pwd`
+pub async fn pwd(ctx: &ShellContext, mut output: Output) -> Output {
+    let current = ctx.cwd();
+    output.println(&current.display().to_string()).await;
+    output
+}
+
+/// `pushd [<dir>]`: saves the current directory and changes to another, or swaps with the last
+/// one saved.
+pub async fn pushd(args: &[String], ctx: &ShellContext, mut output: Output) -> Output {
+    let result = {
+        let mut directories = ctx.directories.lock().unwrap();
+        let current = directories.current.clone();
+        let result = match args {
+            [] => match directories.stack.pop() {
+                Some(top) => {
+                    let result = directories.change(&top);
+                    if result.is_err() {
+                        directories.stack.push(top);
+                    }
+                    result
+                }
+                None => Err("no other directory".to_string()),
+            },
+            [dir] => directories.change(dir),
+            _ => Err("too many arguments".to_string()),
+        };
+        result.map(|()| {
+            directories.stack.push(current);
+            directories.list()
+        })
+    };
+
+    match result {
+        Ok(list) => output.println(&list).await,
+        Err(e) => {
+            output.eprintln(&format!("pushd: {}", e)).await;
+            output.status = 1;
+        }
+    }
+    output
+}
+
+/// `popd`: changes back to the last directory saved by `pushd`.
+pub async fn popd(ctx: &ShellContext, mut output: Output) -> Output {
+    let result = {
+        let mut directories = ctx.directories.lock().unwrap();
+        match directories.stack.pop() {
+            Some(top) => {
+                let result = directories.change(&top);
+                if result.is_err() {
+                    directories.stack.push(top);
+                }
+                result.map(|()| directories.list())
+            }
+            None => Err("directory stack empty".to_string()),
+        }
+    };
+
+    match result {
+        Ok(list) => output.println(&list).await,
+        Err(e) => {
+            output.eprintln(&format!("popd: {}", e)).await;
+            output.status = 1;
+        }
+    }
+    output
+}
@@ -0,0 +1,242 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Filename patterns with `*`, `?` and `[...]`. Quoted characters reach here escaped with a
+//! backslash, so they only match themselves.
+
+use std::path::Path;
+
+/// Escapes the characters that have a meaning in patterns.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn unescape(pattern: &str) -> String {
+    let mut text = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => text.extend(chars.next()),
+            c => text.push(c),
+        }
+    }
+    text
+}
+
+/// Whether the pattern has any unescaped `*`, `?` or `[...]`.
+pub fn is_pattern(pattern: &str) -> bool {
+    let chars = pattern.chars().collect::<Vec<_>>();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '*' | '?' => return true,
+            '[' if class_end(&chars, i).is_some() => return true,
+            _ => {}
+        }
+        i += 1;
+    }
+    false
+}
+
+/// Paths matching the pattern, relative to `cwd` unless the pattern is absolute, in sorted order.
+/// Names starting with `.` are only matched by a pattern that starts with one too.
+pub fn glob(pattern: &str, cwd: &Path) -> Vec<String> {
+    let (mut paths, rest) = match pattern.strip_prefix('/') {
+        Some(rest) => (vec!["/".to_string()], rest),
+        None => (vec![String::new()], pattern),
+    };
+
+    let components = rest.split('/').collect::<Vec<_>>();
+    for (i, component) in components.iter().enumerate() {
+        let last = i == components.len() - 1;
+        if component.is_empty() {
+            // A trailing `/` only matches directories.
+            if last {
+                paths.retain(|path| cwd.join(path).is_dir());
+            }
+            continue;
+        }
+
+        let mut matched = vec![];
+        for path in paths {
+            let dir = cwd.join(&path);
+            if !is_pattern(component) {
+                let path = path + &unescape(component);
+                if cwd.join(&path).symlink_metadata().is_ok() {
+                    matched.push(path);
+                }
+            } else if let Ok(entries) = dir.read_dir() {
+                let chars = component.chars().collect::<Vec<_>>();
+                let mut names = entries
+                    .flatten()
+                    .filter_map(|entry| entry.file_name().into_string().ok())
+                    .filter(|name| !name.starts_with('.') || unescape(component).starts_with('.'))
+                    .filter(|name| matches(&chars, &name.chars().collect::<Vec<_>>()))
+                    .collect::<Vec<_>>();
+                names.sort();
+                matched.extend(names.into_iter().map(|name| path.clone() + &name));
+            }
+        }
+
+        paths = matched;
+        if !last {
+            paths.retain(|path| cwd.join(path).is_dir());
+            for path in &mut paths {
+                path.push('/');
+            }
+        }
+    }
+
+    paths
+}
+
+/// Whether a whole name matches the pattern. On a mismatch after a `*`, the `*` takes one more
+/// character and matching resumes from there, so only the last `*` is ever retried.
+fn matches(pattern: &[char], name: &[char]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    let mut star = None;
+    while n < name.len() {
+        if pattern.get(p) == Some(&'*') {
+            p += 1;
+            star = Some((p, n));
+        } else if let Some(next) = matches_one(pattern, p, name[n]) {
+            p = next;
+            n += 1;
+        } else if let Some((after, taken)) = star {
+            p = after;
+            n = taken + 1;
+            star = Some((after, n));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Where the pattern continues if its part at `p` matches the single character `c`.
+fn matches_one(pattern: &[char], p: usize, c: char) -> Option<usize> {
+    match *pattern.get(p)? {
+        '?' => Some(p + 1),
+        '[' => match class_end(pattern, p) {
+            Some(end) => in_class(&pattern[p + 1..end], c).then_some(end + 1),
+            None => (c == '[').then_some(p + 1),
+        },
+        '\\' if p + 1 < pattern.len() => (pattern[p + 1] == c).then_some(p + 2),
+        literal => (literal == c).then_some(p + 1),
+    }
+}
+
+/// Position of the `]` closing the class opened at `start`. A `]` straight after the `[` or
+/// `[!` is part of the class.
+fn class_end(pattern: &[char], start: usize) -> Option<usize> {
+    let mut i = start + 1;
+    if matches!(pattern.get(i), Some('!' | '^')) {
+        i += 1;
+    }
+    if pattern.get(i) == Some(&']') {
+        i += 1;
+    }
+    while i < pattern.len() {
+        match pattern[i] {
+            '\\' => i += 1,
+            ']' => return Some(i),
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Whether `c` is in a class like `a-z_`, or `!0-9` for the opposite.
+fn in_class(class: &[char], c: char) -> bool {
+    let (negated, class) = match class.first() {
+        Some('!' | '^') => (true, &class[1..]),
+        _ => (false, class),
+    };
+
+    let mut found = false;
+    let mut i = 0;
+    while i < class.len() {
+        let mut low = class[i];
+        if low == '\\' && i + 1 < class.len() {
+            i += 1;
+            low = class[i];
+        }
+        if class.get(i + 1) == Some(&'-') && i + 2 < class.len() {
+            found |= (low..=class[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            found |= low == c;
+            i += 1;
+        }
+    }
+
+    found != negated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob() {
+        let matches = |pattern: &str, name: &str| {
+            let pattern = pattern.chars().collect::<Vec<_>>();
+            super::matches(&pattern, &name.chars().collect::<Vec<_>>())
+        };
+
+        assert!(matches("*.rs", "main.rs"));
+        assert!(matches("*", ""));
+        assert!(!matches("*.rs", "main.py"));
+        assert!(matches("a?c", "abc"));
+        assert!(!matches("a?c", "ac"));
+        assert!(matches("[a-c]x", "bx"));
+        assert!(!matches("[!a-c]x", "bx"));
+        assert!(matches("[]]", "]"));
+        assert!(matches("a[", "a["));
+        assert!(matches("\\*", "*"));
+        assert!(!matches("\\*", "x"));
+        assert!(matches("a*b*c", "aXbYbZc"));
+        assert!(!matches("a*b*c", "aXbYbZ"));
+        assert!(matches(
+            "*a*a*a*a*a*a*a*a*b",
+            &format!("{}b", "a".repeat(1000))
+        ));
+        assert!(!matches("*a*a*a*a*a*a*a*a*b", &"a".repeat(1000)));
+
+        assert!(is_pattern("src/*.rs"));
+        assert!(!is_pattern(&escape("*.txt")));
+        assert!(!is_pattern("["));
+
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        assert_eq!(glob("Cargo.t*", dir), ["Cargo.toml"]);
+        assert_eq!(glob("src/m?.rs", dir), ["src/md.rs"]);
+        assert_eq!(
+            glob("src/shell/[pv]a*.rs", dir),
+            ["src/shell/parser.rs", "src/shell/variables.rs"]
+        );
+        assert_eq!(glob("s*/", dir), ["src/"]);
+        assert_eq!(glob("nothing*", dir), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,553 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Splits a command line into words and operators.
+
+use std::fmt;
+
+use super::{
+    ast::{Word, WordPart},
+    parser::{self, ParseError},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Word(Word),
+    /// `;` or a newline.
+    Semicolon,
+    /// The end of a line, which separates commands like `;` but can also come between them.
+    Newline,
+    /// `&`
+    Ampersand,
+    /// `&&`
+    And,
+    /// `||`
+    Or,
+    /// `|`
+    Pipe,
+    /// `<`, `<<`, `>`, `>>`, `>&`, `&>` or `&>>`, optionally after a stream number as in `2>`.
+    /// A `<<` is followed by a word holding the here-document's text.
+    Redirect {
+        fd: Option<u32>,
+        op: RedirectOp,
+    },
+    /// A `)` that doesn't close a `$(`.
+    RParen,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) => match word.literal() {
+                Some(keyword) if parser::KEYWORDS.contains(&keyword) => write!(f, "`{}`", keyword),
+                _ => write!(f, "word"),
+            },
+            Token::Semicolon => write!(f, "`;`"),
+            Token::Newline => write!(f, "newline"),
+            Token::Ampersand => write!(f, "`&`"),
+            Token::And => write!(f, "`&&`"),
+            Token::Or => write!(f, "`||`"),
+            Token::Pipe => write!(f, "`|`"),
+            Token::Redirect { fd, op } => {
+                let fd = fd.map(|fd| fd.to_string()).unwrap_or_default();
+                write!(f, "`{}{}`", fd, op)
+            }
+            Token::RParen => write!(f, "`)`"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectOp {
+    /// `<`
+    Read,
+    /// `<<`: the following lines, up to a delimiter.
+    HereDoc,
+    /// `>`
+    Write,
+    /// `>>`
+    Append,
+    /// `>&`
+    Duplicate,
+    /// `&>`: stdout and stderr.
+    WriteBoth,
+    /// `&>>`: stdout and stderr.
+    AppendBoth,
+}
+
+impl fmt::Display for RedirectOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            RedirectOp::Read => "<",
+            RedirectOp::HereDoc => "<<",
+            RedirectOp::Write => ">",
+            RedirectOp::Append => ">>",
+            RedirectOp::Duplicate => ">&",
+            RedirectOp::WriteBoth => "&>",
+            RedirectOp::AppendBoth => "&>>",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+pub fn lex(input: &str) -> Result<Vec<Token>, ParseError> {
+    let mut lexer = Lexer {
+        chars: input.chars().collect(),
+        position: 0,
+    };
+    lexer.tokens(false)
+}
+
+/// Characters that end an unquoted word.
+fn is_word_end(c: char) -> bool {
+    c.is_whitespace() || matches!(c, ';' | '&' | '|' | '<' | '>' | ')')
+}
+
+pub fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+struct Lexer {
+    chars: Vec<char>,
+    position: usize,
+}
+
+/// A `<<` whose text starts on the next line.
+struct PendingHereDoc {
+    /// Where the word for the text goes in the tokens.
+    index: usize,
+    delimiter: String,
+    /// Whether any of the delimiter was quoted, which stops expansions in the text.
+    quoted: bool,
+    /// `<<-` removes tabs from the start of each line.
+    strip_tabs: bool,
+}
+
+impl Lexer {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.position += 1;
+        c
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Lexes up to the end of the input, or the `)` closing a substitution if `in_substitution`.
+    fn tokens(&mut self, in_substitution: bool) -> Result<Vec<Token>, ParseError> {
+        let mut tokens = vec![];
+        let mut here_docs: Vec<PendingHereDoc> = vec![];
+
+        loop {
+            while self.peek().is_some_and(|c| c.is_whitespace() && c != '\n') {
+                self.position += 1;
+            }
+
+            let Some(c) = self.peek() else {
+                if let Some(here_doc) = here_docs.first() {
+                    return Err(ParseError::Incomplete(format!(
+                        "here-document ended before `{}`",
+                        here_doc.delimiter
+                    )));
+                }
+                if in_substitution {
+                    return Err(ParseError::Incomplete("unclosed `$(`".to_string()));
+                }
+                return Ok(tokens);
+            };
+
+            match c {
+                '\n' => {
+                    self.next();
+                    tokens.push(Token::Newline);
+                    for here_doc in std::mem::take(&mut here_docs) {
+                        tokens[here_doc.index] = Token::Word(self.here_doc(&here_doc)?);
+                    }
+                }
+                ';' => {
+                    self.next();
+                    tokens.push(Token::Semicolon);
+                }
+                '&' => {
+                    self.next();
+                    tokens.push(if self.eat('&') {
+                        Token::And
+                    } else if self.eat('>') {
+                        let op = if self.eat('>') {
+                            RedirectOp::AppendBoth
+                        } else {
+                            RedirectOp::WriteBoth
+                        };
+                        Token::Redirect { fd: None, op }
+                    } else {
+                        Token::Ampersand
+                    });
+                }
+                '|' => {
+                    self.next();
+                    tokens.push(if self.eat('|') {
+                        Token::Or
+                    } else {
+                        Token::Pipe
+                    });
+                }
+                '<' | '>' => self.push_redirect(None, &mut tokens, &mut here_docs)?,
+                ')' => {
+                    self.next();
+                    if in_substitution {
+                        return Ok(tokens);
+                    }
+                    tokens.push(Token::RParen);
+                }
+                '#' => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.position += 1;
+                    }
+                }
+                _ => match self.stream_number() {
+                    Some(fd) => self.push_redirect(Some(fd), &mut tokens, &mut here_docs)?,
+                    None => tokens.push(Token::Word(self.word()?)),
+                },
+            }
+        }
+    }
+
+    /// Consumes the digits of a stream number if they're directly followed by `<` or `>`.
+    fn stream_number(&mut self) -> Option<u32> {
+        let digits = self.chars[self.position..]
+            .iter()
+            .take_while(|c| c.is_ascii_digit())
+            .count();
+
+        if digits == 0 || !matches!(self.chars.get(self.position + digits), Some('<' | '>')) {
+            return None;
+        }
+
+        let number: String = self.chars[self.position..self.position + digits]
+            .iter()
+            .collect();
+        let fd = number.parse().ok()?;
+        self.position += digits;
+        Some(fd)
+    }
+
+    fn redirect(&mut self, fd: Option<u32>) -> Token {
+        let op = if self.eat('<') {
+            if self.eat('<') {
+                RedirectOp::HereDoc
+            } else {
+                RedirectOp::Read
+            }
+        } else {
+            self.next();
+            if self.eat('>') {
+                RedirectOp::Append
+            } else if self.eat('&') {
+                RedirectOp::Duplicate
+            } else {
+                RedirectOp::Write
+            }
+        };
+
+        Token::Redirect { fd, op }
+    }
+
+    /// Adds a redirect to the tokens. For `<<`, the delimiter is read too, and an empty word
+    /// added for the text, which is filled in at the end of the line.
+    fn push_redirect(
+        &mut self,
+        fd: Option<u32>,
+        tokens: &mut Vec<Token>,
+        here_docs: &mut Vec<PendingHereDoc>,
+    ) -> Result<(), ParseError> {
+        let token = self.redirect(fd);
+        let here_doc = matches!(
+            token,
+            Token::Redirect {
+                op: RedirectOp::HereDoc,
+                ..
+            }
+        );
+        tokens.push(token);
+        if !here_doc {
+            return Ok(());
+        }
+
+        let strip_tabs = self.eat('-');
+        while self.peek().is_some_and(|c| c.is_whitespace() && c != '\n') {
+            self.position += 1;
+        }
+        if self.peek().is_none_or(is_word_end) {
+            return Err(ParseError::Invalid(
+                "expected a delimiter after `<<`".to_string(),
+            ));
+        }
+
+        let word = self.word()?;
+        let mut delimiter = String::new();
+        for part in &word.0 {
+            match part {
+                WordPart::Literal(text) | WordPart::Quoted(text) => delimiter.push_str(text),
+                WordPart::Variable { name, .. } => delimiter.push_str(&format!("${}", name)),
+                WordPart::Substitution { .. } => {
+                    return Err(ParseError::Invalid(
+                        "`$(` isn't allowed in a here-document delimiter".to_string(),
+                    ))
+                }
+            }
+        }
+
+        here_docs.push(PendingHereDoc {
+            index: tokens.len(),
+            delimiter,
+            quoted: word.literal().is_none(),
+            strip_tabs,
+        });
+        tokens.push(Token::Word(Word::default()));
+        Ok(())
+    }
+
+    /// Reads the lines of a here-document, up to the line with just its delimiter.
+    fn here_doc(&mut self, here_doc: &PendingHereDoc) -> Result<Word, ParseError> {
+        let mut text = String::new();
+
+        loop {
+            if self.peek().is_none() {
+                return Err(ParseError::Incomplete(format!(
+                    "here-document ended before `{}`",
+                    here_doc.delimiter
+                )));
+            }
+
+            let mut line = String::new();
+            while let Some(c) = self.next() {
+                if c == '\n' {
+                    break;
+                }
+                line.push(c);
+            }
+
+            let line = if here_doc.strip_tabs {
+                line.trim_start_matches('\t')
+            } else {
+                &line
+            };
+            if line == here_doc.delimiter {
+                break;
+            }
+            text.push_str(line);
+            text.push('\n');
+        }
+
+        if here_doc.quoted {
+            return Ok(Word(vec![WordPart::Quoted(text)]));
+        }
+
+        // Like double quotes, except that `"` has no meaning.
+        let mut lexer = Lexer {
+            chars: text.chars().collect(),
+            position: 0,
+        };
+        let mut parts = vec![];
+        let mut text = String::new();
+        while let Some(c) = lexer.peek() {
+            match c {
+                '\\' => {
+                    lexer.next();
+                    match lexer.next() {
+                        Some(c @ ('$' | '\\' | '`')) => text.push(c),
+                        Some('\n') => {}
+                        Some(c) => {
+                            text.push('\\');
+                            text.push(c);
+                        }
+                        None => text.push('\\'),
+                    }
+                }
+                '$' => {
+                    if !text.is_empty() {
+                        parts.push(WordPart::Quoted(std::mem::take(&mut text)));
+                    }
+                    parts.push(lexer.dollar(true)?);
+                }
+                c => {
+                    lexer.next();
+                    text.push(c);
+                }
+            }
+        }
+        if !text.is_empty() || parts.is_empty() {
+            parts.push(WordPart::Quoted(text));
+        }
+
+        Ok(Word(parts))
+    }
+
+    fn word(&mut self) -> Result<Word, ParseError> {
+        let mut parts = vec![];
+        let mut literal = String::new();
+
+        fn flush(literal: &mut String, parts: &mut Vec<WordPart>) {
+            if !literal.is_empty() {
+                parts.push(WordPart::Literal(std::mem::take(literal)));
+            }
+        }
+
+        while let Some(c) = self.peek() {
+            if is_word_end(c) {
+                break;
+            }
+
+            match c {
+                '\\' => {
+                    self.next();
+                    match self.next() {
+                        // Line continuation.
+                        Some('\n') => {}
+                        Some(c) => {
+                            flush(&mut literal, &mut parts);
+                            parts.push(WordPart::Quoted(c.to_string()));
+                        }
+                        None => return Err(ParseError::Incomplete("trailing `\\`".to_string())),
+                    }
+                }
+                '\'' => {
+                    flush(&mut literal, &mut parts);
+                    parts.push(WordPart::Quoted(self.single_quoted()?));
+                }
+                '"' => {
+                    flush(&mut literal, &mut parts);
+                    parts.extend(self.double_quoted()?);
+                }
+                '$' => {
+                    flush(&mut literal, &mut parts);
+                    parts.push(self.dollar(false)?);
+                }
+                c => {
+                    self.next();
+                    literal.push(c);
+                }
+            }
+        }
+
+        flush(&mut literal, &mut parts);
+        Ok(Word(parts))
+    }
+
+    fn single_quoted(&mut self) -> Result<String, ParseError> {
+        self.next();
+        let mut text = String::new();
+
+        loop {
+            match self.next() {
+                Some('\'') => return Ok(text),
+                Some(c) => text.push(c),
+                None => return Err(ParseError::Incomplete("unclosed `'`".to_string())),
+            }
+        }
+    }
+
+    /// Text in double quotes is taken as is, apart from `$` expansions and backslash escapes.
+    fn double_quoted(&mut self) -> Result<Vec<WordPart>, ParseError> {
+        self.next();
+        let mut parts = vec![];
+        let mut text = String::new();
+
+        loop {
+            match self.next() {
+                Some('"') => break,
+                Some('\\') => match self.next() {
+                    Some('n') => text.push('\n'),
+                    Some('t') => text.push('\t'),
+                    Some(c @ ('"' | '\\' | '$' | '`')) => text.push(c),
+                    Some('\n') => {}
+                    Some(c) => {
+                        text.push('\\');
+                        text.push(c);
+                    }
+                    None => return Err(ParseError::Incomplete("unclosed `\"`".to_string())),
+                },
+                Some('$') => {
+                    self.position -= 1;
+                    if !text.is_empty() {
+                        parts.push(WordPart::Quoted(std::mem::take(&mut text)));
+                    }
+                    parts.push(self.dollar(true)?);
+                }
+                Some(c) => text.push(c),
+                None => return Err(ParseError::Incomplete("unclosed `\"`".to_string())),
+            }
+        }
+
+        // Keep the text even if it's empty, so `""` is still an argument.
+        if !text.is_empty() || parts.is_empty() {
+            parts.push(WordPart::Quoted(text));
+        }
+
+        Ok(parts)
+    }
+
+    /// `$name`, `${name}`, `$?`, `$#`, `$@` or `$(...)`. A `$` followed by anything else is just a `$`.
+    fn dollar(&mut self, quoted: bool) -> Result<WordPart, ParseError> {
+        self.next();
+
+        if self.eat('(') {
+            let tokens = self.tokens(true)?;
+            let list = parser::parse(tokens)?;
+            return Ok(WordPart::Substitution { list, quoted });
+        }
+
+        let mut name = String::new();
+
+        if self.eat('{') {
+            loop {
+                match self.next() {
+                    Some('}') => break,
+                    Some(c) => name.push(c),
+                    None => return Err(ParseError::Incomplete("unclosed `${`".to_string())),
+                }
+            }
+        } else if let Some(c) = self.peek().filter(|c| matches!(c, '?' | '#' | '@')) {
+            self.next();
+            name.push(c);
+        } else {
+            while let Some(c) = self.peek().filter(|c| is_name_char(*c)) {
+                self.next();
+                name.push(c);
+            }
+        }
+
+        if name.is_empty() {
+            let dollar = "$".to_string();
+            return Ok(if quoted {
+                WordPart::Quoted(dollar)
+            } else {
+                WordPart::Literal(dollar)
+            });
+        }
+
+        Ok(WordPart::Variable { name, quoted })
+    }
+}
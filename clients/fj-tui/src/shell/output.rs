@@ -0,0 +1,303 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{path::PathBuf, sync::Arc};
+
+use tokio::{
+    fs::File,
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream},
+    sync::Mutex,
+};
+
+use crate::app::{Event, EventSender};
+
+/// Longest partial line held back from the console before it's shown anyway.
+const MAX_LINE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputMode {
+    #[default]
+    ToConsole,
+    Piped,
+}
+
+/// Where one of a command's streams goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Sink {
+    /// Shown in the console, coloured as the given stream.
+    Console(Stream),
+    /// Kept in `stdout`, for a substitution.
+    Capture,
+    /// Written to `writers[i]`.
+    Writer(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// The next command in a pipeline, or a file. Shared by every command inside a compound
+/// command, and closed once the last of them finishes.
+struct Writer {
+    /// The file's path, for errors. Pipes have none.
+    path: Option<PathBuf>,
+    /// Taken away once a pipe's reader has gone.
+    stream: Mutex<Option<Box<dyn AsyncWrite + Send + Unpin>>>,
+}
+
+impl Writer {
+    fn new(path: Option<PathBuf>, stream: impl AsyncWrite + Send + Unpin + 'static) -> Arc<Self> {
+        Arc::new(Self {
+            path,
+            stream: Mutex::new(Some(Box::new(stream))),
+        })
+    }
+
+    /// Whether a pipe's reader has gone. A writer in use is taken to still be open.
+    fn is_closed(&self) -> bool {
+        self.stream.try_lock().is_ok_and(|stream| stream.is_none())
+    }
+}
+
+pub struct Output {
+    pub status: i32,
+    pub stdout: String,
+    stdout_sink: Sink,
+    stderr_sink: Sink,
+    /// Pipes and files the command's streams can be sent to.
+    writers: Vec<Arc<Writer>>,
+    /// The end of each stream's output without its newline yet, for `write_bytes`.
+    partial_stdout: Vec<u8>,
+    partial_stderr: Vec<u8>,
+    events: EventSender,
+}
+
+impl Output {
+    pub fn new(mode: OutputMode, events: EventSender) -> Self {
+        let stdout_sink = match mode {
+            OutputMode::ToConsole => Sink::Console(Stream::Stdout),
+            OutputMode::Piped => Sink::Capture,
+        };
+
+        Self {
+            stdout_sink,
+            stderr_sink: Sink::Console(Stream::Stderr),
+            writers: vec![],
+            partial_stdout: vec![],
+            partial_stderr: vec![],
+            events,
+            status: 0,
+            stdout: String::new(),
+        }
+    }
+
+    /// Output for a command inside this one, going wherever this one's goes.
+    pub fn inner(&self) -> Self {
+        Self {
+            stdout_sink: self.stdout_sink,
+            stderr_sink: self.stderr_sink,
+            writers: self.writers.clone(),
+            partial_stdout: vec![],
+            partial_stderr: vec![],
+            events: self.events.clone(),
+            status: 0,
+            stdout: String::new(),
+        }
+    }
+
+    /// Output for a command inside this one whose output is written to the next command in a
+    /// pipeline. Errors still go wherever this one's go.
+    pub fn piped(&self, pipe: DuplexStream) -> Self {
+        let mut output = self.inner();
+        output.stdout_sink = Sink::Writer(output.writers.len());
+        output.writers.push(Writer::new(None, pipe));
+        output
+    }
+
+    pub fn append(&mut self, other: Output) {
+        self.stdout.push_str(&other.stdout);
+        self.status = other.status;
+    }
+
+    /// Sends `stream` to `file` from now on.
+    pub fn redirect_to_file(&mut self, stream: Stream, path: PathBuf, file: File) {
+        let sink = Sink::Writer(self.writers.len());
+        self.writers.push(Writer::new(Some(path), file));
+        match stream {
+            Stream::Stdout => self.stdout_sink = sink,
+            Stream::Stderr => self.stderr_sink = sink,
+        }
+    }
+
+    /// Sends `stream` wherever `target` currently goes, as with `2>&1`.
+    pub fn duplicate(&mut self, stream: Stream, target: Stream) {
+        let sink = match target {
+            Stream::Stdout => self.stdout_sink,
+            Stream::Stderr => self.stderr_sink,
+        };
+        match stream {
+            Stream::Stdout => self.stdout_sink = sink,
+            Stream::Stderr => self.stderr_sink = sink,
+        }
+    }
+
+    /// Whether the next command in the pipeline has stopped reading, so there's no point in
+    /// producing more output.
+    pub fn is_broken(&self) -> bool {
+        match self.stdout_sink {
+            Sink::Writer(i) => self.writers.get(i).is_none_or(|writer| writer.is_closed()),
+            _ => false,
+        }
+    }
+
+    pub async fn println(&mut self, s: &str) {
+        // Normal output sent to stderr still looks like stderr.
+        let event = match self.stdout_sink {
+            Sink::Console(Stream::Stderr) => Event::Stderr,
+            _ => Event::Print,
+        };
+        self.write(self.stdout_sink, s, event).await;
+    }
+
+    /// An error from a built-in command.
+    pub async fn eprintln(&mut self, s: &str) {
+        self.write(self.stderr_sink, s, Event::EPrint).await;
+    }
+
+    /// Error output of an external command.
+    pub async fn print_stderr(&mut self, s: &str) {
+        self.write(self.stderr_sink, s, Event::Stderr).await;
+    }
+
+    /// Copies all of `reader` to stdout as it's read, without holding all of it in memory.
+    pub async fn print_all(&mut self, reader: &mut (impl AsyncRead + Unpin)) {
+        let mut buffer = vec![0; 8 * 1024];
+        while let Ok(n @ 1..) = reader.read(&mut buffer).await {
+            self.write_bytes(Stream::Stdout, &buffer[..n]).await;
+            if self.is_broken() {
+                break;
+            }
+        }
+        self.end_bytes().await;
+    }
+
+    /// Writes part of a stream's output as it comes. Pipes and files get exactly these bytes, and
+    /// the console and substitutions get each line once it's complete. Call `end_bytes` after the
+    /// last of it.
+    pub async fn write_bytes(&mut self, stream: Stream, bytes: &[u8]) {
+        let sink = match stream {
+            Stream::Stdout => self.stdout_sink,
+            Stream::Stderr => self.stderr_sink,
+        };
+        if let Sink::Writer(i) = sink {
+            self.write_raw(i, bytes).await;
+            return;
+        }
+
+        let partial = match stream {
+            Stream::Stdout => &mut self.partial_stdout,
+            Stream::Stderr => &mut self.partial_stderr,
+        };
+        partial.extend_from_slice(bytes);
+        let mut lines = vec![];
+        while let Some(end) = partial.iter().position(|&b| b == b'\n') {
+            let line = partial.drain(..=end).collect::<Vec<_>>();
+            lines.push(String::from_utf8_lossy(&line[..end]).into_owned());
+        }
+        if partial.len() > MAX_LINE {
+            lines.push(String::from_utf8_lossy(partial).into_owned());
+            partial.clear();
+        }
+
+        for line in lines {
+            self.print_line(stream, &line).await;
+        }
+    }
+
+    /// Prints the last line given to `write_bytes` if it didn't end in a newline.
+    pub async fn end_bytes(&mut self) {
+        for stream in [Stream::Stdout, Stream::Stderr] {
+            let partial = match stream {
+                Stream::Stdout => std::mem::take(&mut self.partial_stdout),
+                Stream::Stderr => std::mem::take(&mut self.partial_stderr),
+            };
+            if !partial.is_empty() {
+                self.print_line(stream, &String::from_utf8_lossy(&partial))
+                    .await;
+            }
+        }
+    }
+
+    async fn print_line(&mut self, stream: Stream, line: &str) {
+        match stream {
+            Stream::Stdout => self.println(line).await,
+            Stream::Stderr => self.print_stderr(line).await,
+        }
+    }
+
+    async fn write(&mut self, sink: Sink, s: &str, event: fn(String) -> Event) {
+        match sink {
+            Sink::Console(_) => {
+                let _ = self.events.send(event(s.to_string()));
+            }
+            Sink::Capture => {
+                self.stdout.push_str(s);
+                self.stdout.push('\n');
+            }
+            Sink::Writer(i) => self.write_raw(i, format!("{}\n", s).as_bytes()).await,
+        }
+    }
+
+    async fn write_raw(&self, writer: usize, bytes: &[u8]) {
+        let Some(writer) = self.writers.get(writer) else {
+            return;
+        };
+
+        let mut stream = writer.stream.lock().await;
+        let Some(open) = stream.as_mut() else {
+            return;
+        };
+        if let Err(e) = open.write_all(bytes).await {
+            match &writer.path {
+                Some(path) => {
+                    let message = format!("{}: {}", path.display(), e);
+                    let _ = self.events.send(Event::EPrint(message));
+                }
+                // The reader has gone, like `head` once it has enough.
+                None => *stream = None,
+            }
+        }
+    }
+
+    /// Lets go of the pipes and files, flushing them first. Once every command sharing a pipe has
+    /// finished, the next command sees the end of its input.
+    pub async fn finish(&mut self) {
+        for writer in self.writers.drain(..) {
+            let mut stream = writer.stream.lock().await;
+            let Some(stream) = stream.as_mut() else {
+                continue;
+            };
+            // Tokio finishes writes in the background, so the next command might not see them.
+            if let Err(e) = stream.flush().await {
+                if let Some(path) = &writer.path {
+                    let message = format!("{}: {}", path.display(), e);
+                    let _ = self.events.send(Event::EPrint(message));
+                }
+            }
+        }
+    }
+}
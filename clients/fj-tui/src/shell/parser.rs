@@ -0,0 +1,624 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Builds the syntax tree from the lexer's tokens.
+
+use std::fmt;
+
+use super::{
+    ast::{
+        AndOr, Command, Compound, Connector, List, ListItem, Pipeline, Redirect, RedirectKind,
+        SimpleCommand, Word, WordPart,
+    },
+    lexer::{self, RedirectOp, Token},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// The input ended early, e.g. in a quote or after `|`. More input could complete it.
+    Incomplete(String),
+    Invalid(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Incomplete(e) | ParseError::Invalid(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Words with a meaning of their own at the start of a command.
+pub const KEYWORDS: &[&str] = &[
+    "if", "then", "elif", "else", "fi", "for", "in", "while", "do", "done", "{", "}", "function",
+];
+
+/// Keywords that end the list before them.
+const TERMINATORS: &[&str] = &["then", "elif", "else", "fi", "do", "done", "}"];
+
+/// Parses a whole command line.
+pub fn parse_line(input: &str) -> Result<List, ParseError> {
+    parse(lexer::lex(input)?)
+}
+
+pub fn parse(tokens: Vec<Token>) -> Result<List, ParseError> {
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        depth: 0,
+    };
+
+    let list = parser.list()?;
+
+    match parser.peek() {
+        None => Ok(list),
+        Some(token) => Err(ParseError::Invalid(format!("unexpected {}", token))),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// How many lists the parser is inside, with 1 for the line itself.
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The keyword next in the input, if the next token is one.
+    fn keyword(&self) -> Option<&str> {
+        match self.peek() {
+            Some(Token::Word(word)) => word.literal().filter(|w| KEYWORDS.contains(w)),
+            _ => None,
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if self.keyword() == Some(keyword) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        if self.eat_keyword(keyword) {
+            return Ok(());
+        }
+
+        Err(match self.peek() {
+            None => ParseError::Incomplete(format!("expected `{}`", keyword)),
+            Some(token) => ParseError::Invalid(format!("expected `{}`, found {}", keyword, token)),
+        })
+    }
+
+    fn at_end(&self) -> bool {
+        self.peek().is_none() || self.keyword().is_some_and(|k| TERMINATORS.contains(&k))
+    }
+
+    fn list(&mut self) -> Result<List, ParseError> {
+        self.depth += 1;
+        let list = self.list_items();
+        self.depth -= 1;
+        list
+    }
+
+    fn list_items(&mut self) -> Result<List, ParseError> {
+        let mut items = vec![];
+
+        loop {
+            self.skip_newlines();
+
+            if self.at_end() {
+                return Ok(List(items));
+            }
+
+            let and_or = self.and_or()?;
+            let background = self.eat(&Token::Ampersand);
+            // A `;` with no command before it, as in `a; ; b` or `a &; b`, is an error.
+            let separated = background || self.eat(&Token::Semicolon) || self.eat(&Token::Newline);
+            if !separated && !self.at_end() || self.peek() == Some(&Token::Semicolon) {
+                let token = self.peek().unwrap();
+                return Err(ParseError::Invalid(format!("unexpected {}", token)));
+            }
+
+            // Each command line is one job, so only the whole line can go in the background.
+            let more = self.tokens[self.position..]
+                .iter()
+                .any(|token| *token != Token::Newline);
+            if background && (self.depth > 1 || more) {
+                return Err(ParseError::Invalid(
+                    "`&` can only end a command line".to_string(),
+                ));
+            }
+            items.push(ListItem { and_or, background });
+        }
+    }
+
+    fn skip_newlines(&mut self) {
+        while self.eat(&Token::Newline) {}
+    }
+
+    fn and_or(&mut self) -> Result<AndOr, ParseError> {
+        let first = self.pipeline()?;
+        let mut rest = vec![];
+
+        loop {
+            let connector = if self.eat(&Token::And) {
+                Connector::And
+            } else if self.eat(&Token::Or) {
+                Connector::Or
+            } else {
+                return Ok(AndOr { first, rest });
+            };
+
+            // The next pipeline can be on the next line.
+            self.skip_newlines();
+
+            rest.push((connector, self.pipeline()?));
+        }
+    }
+
+    fn pipeline(&mut self) -> Result<Pipeline, ParseError> {
+        let mut commands = vec![self.command()?];
+
+        while self.eat(&Token::Pipe) {
+            commands.push(self.command()?);
+        }
+
+        Ok(Pipeline(commands))
+    }
+
+    fn command(&mut self) -> Result<Command, ParseError> {
+        if let Some(name) = self.function_name()? {
+            let body = self.group()?;
+            return Ok(Command::Function { name, body });
+        }
+
+        let compound = if self.eat_keyword("if") {
+            self.if_clause()?
+        } else if self.eat_keyword("for") {
+            self.for_clause()?
+        } else if self.eat_keyword("while") {
+            let condition = self.list()?;
+            self.expect_keyword("do")?;
+            let body = self.list()?;
+            self.expect_keyword("done")?;
+            Compound::While { condition, body }
+        } else if self.keyword() == Some("{") {
+            Compound::Group(self.group()?)
+        } else {
+            return self.simple_command().map(Command::Simple);
+        };
+
+        let mut redirects = vec![];
+        while let Some(&Token::Redirect { fd, op }) = self.peek() {
+            redirects.extend(self.redirect(fd, op)?);
+        }
+
+        Ok(Command::Compound(compound, redirects))
+    }
+
+    /// The name in `function name`, `name()` or `name ()`, consuming it if the next command is a
+    /// function definition.
+    fn function_name(&mut self) -> Result<Option<String>, ParseError> {
+        let is_name = |name: &str| {
+            !name.is_empty()
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+                && name.chars().all(|c| lexer::is_name_char(c) || c == '-')
+        };
+
+        let (name, length) = if self.eat_keyword("function") {
+            match (self.parenthesised_name(), self.literal(0)) {
+                (Some(found), _) => found,
+                (None, Some(name)) => (name.to_string(), 1),
+                (None, None) if self.peek().is_none() => {
+                    return Err(ParseError::Incomplete(
+                        "expected a name after `function`".to_string(),
+                    ))
+                }
+                (None, None) => {
+                    return Err(ParseError::Invalid(
+                        "expected a name after `function`".to_string(),
+                    ))
+                }
+            }
+        } else {
+            match self.parenthesised_name() {
+                Some(found) => found,
+                None => return Ok(None),
+            }
+        };
+
+        if !is_name(&name) {
+            return Err(ParseError::Invalid(format!(
+                "`{}`: not a valid function name",
+                name
+            )));
+        }
+        self.position += length;
+        Ok(Some(name))
+    }
+
+    /// The text of the word `offset` tokens ahead, if it's plain text.
+    fn literal(&self, offset: usize) -> Option<&str> {
+        match self.tokens.get(self.position + offset) {
+            Some(Token::Word(word)) => word.literal(),
+            _ => None,
+        }
+    }
+
+    /// `name()` or `name ()`, which are lexed as `name(` or `name` `(`, then `)`. Returns the
+    /// name and how many tokens it took up.
+    fn parenthesised_name(&self) -> Option<(String, usize)> {
+        let first = self.literal(0)?;
+        let closed = |offset| self.tokens.get(self.position + offset) == Some(&Token::RParen);
+
+        match first.strip_suffix('(') {
+            Some(name) if closed(1) => Some((name.to_string(), 2)),
+            _ if self.literal(1) == Some("(") && closed(2) => Some((first.to_string(), 3)),
+            _ => None,
+        }
+    }
+
+    /// `{ ... }`
+    fn group(&mut self) -> Result<List, ParseError> {
+        self.skip_newlines();
+        self.expect_keyword("{")?;
+        let list = self.list()?;
+        self.expect_keyword("}")?;
+        Ok(list)
+    }
+
+    /// The rest of an `if`, after the keyword.
+    fn if_clause(&mut self) -> Result<Compound, ParseError> {
+        let mut branches = vec![];
+
+        loop {
+            let condition = self.list()?;
+            self.expect_keyword("then")?;
+            branches.push((condition, self.list()?));
+
+            if !self.eat_keyword("elif") {
+                break;
+            }
+        }
+
+        let otherwise = if self.eat_keyword("else") {
+            Some(self.list()?)
+        } else {
+            None
+        };
+        self.expect_keyword("fi")?;
+
+        Ok(Compound::If {
+            branches,
+            otherwise,
+        })
+    }
+
+    /// The rest of a `for`, after the keyword.
+    fn for_clause(&mut self) -> Result<Compound, ParseError> {
+        let name = match self.peek() {
+            Some(Token::Word(word)) => word
+                .literal()
+                .filter(|name| {
+                    !name.starts_with(|c: char| c.is_ascii_digit())
+                        && name.chars().all(lexer::is_name_char)
+                })
+                .map(String::from)
+                .ok_or_else(|| ParseError::Invalid("expected a name after `for`".to_string()))?,
+            None => {
+                return Err(ParseError::Incomplete(
+                    "expected a name after `for`".to_string(),
+                ))
+            }
+            Some(token) => {
+                return Err(ParseError::Invalid(format!(
+                    "expected a name after `for`, found {}",
+                    token
+                )))
+            }
+        };
+        self.position += 1;
+
+        self.expect_keyword("in")?;
+        let mut words = vec![];
+        while let Some(Token::Word(word)) = self.peek() {
+            words.push(word.clone());
+            self.position += 1;
+        }
+
+        self.eat(&Token::Semicolon);
+        self.skip_newlines();
+        self.expect_keyword("do")?;
+        let body = self.list()?;
+        self.expect_keyword("done")?;
+
+        Ok(Compound::For { name, words, body })
+    }
+
+    fn simple_command(&mut self) -> Result<SimpleCommand, ParseError> {
+        let mut words = vec![];
+        let mut redirects = vec![];
+
+        loop {
+            match self.peek() {
+                Some(Token::Word(word)) => {
+                    words.push(word.clone());
+                    self.position += 1;
+                }
+                Some(&Token::Redirect { fd, op }) => redirects.extend(self.redirect(fd, op)?),
+                _ => break,
+            }
+        }
+
+        if words.is_empty() && redirects.is_empty() {
+            return Err(match self.peek() {
+                None => ParseError::Incomplete("expected a command".to_string()),
+                Some(token) => ParseError::Invalid(format!("unexpected {}", token)),
+            });
+        }
+
+        Ok(SimpleCommand { words, redirects })
+    }
+
+    /// A redirect operator and the word after it.
+    fn redirect(&mut self, fd: Option<u32>, op: RedirectOp) -> Result<Vec<Redirect>, ParseError> {
+        let operator = self.peek().unwrap().to_string();
+        self.position += 1;
+
+        let target = match self.peek() {
+            Some(Token::Word(target)) => target.clone(),
+            None => {
+                return Err(ParseError::Incomplete(format!(
+                    "expected a file after {}",
+                    operator
+                )))
+            }
+            Some(token) => {
+                return Err(ParseError::Invalid(format!(
+                    "expected a file after {}, found {}",
+                    operator, token
+                )))
+            }
+        };
+        self.position += 1;
+
+        Ok(redirect(fd, op, target))
+    }
+}
+
+fn redirect(fd: Option<u32>, op: RedirectOp, target: Word) -> Vec<Redirect> {
+    let simple = |default_fd, kind| {
+        vec![Redirect {
+            fd: fd.unwrap_or(default_fd),
+            kind,
+            target: target.clone(),
+        }]
+    };
+
+    // `&> file` is short for `> file 2>&1`.
+    let both = |kind| {
+        vec![
+            Redirect {
+                fd: 1,
+                kind,
+                target: target.clone(),
+            },
+            Redirect {
+                fd: 2,
+                kind: RedirectKind::Duplicate,
+                target: Word(vec![WordPart::Literal("1".to_string())]),
+            },
+        ]
+    };
+
+    match op {
+        RedirectOp::Read => simple(0, RedirectKind::Read),
+        RedirectOp::HereDoc => simple(0, RedirectKind::HereDoc),
+        RedirectOp::Write => simple(1, RedirectKind::Write),
+        RedirectOp::Append => simple(1, RedirectKind::Append),
+        RedirectOp::Duplicate => simple(1, RedirectKind::Duplicate),
+        RedirectOp::WriteBoth => both(RedirectKind::Write),
+        RedirectOp::AppendBoth => both(RedirectKind::Append),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::ast::{Word, WordPart};
+    use super::*;
+
+    fn words(command: &Command) -> Vec<String> {
+        let Command::Simple(command) = command else {
+            panic!("expected a simple command, found {:?}", command);
+        };
+        command
+            .words
+            .iter()
+            .map(|Word(parts)| {
+                parts
+                    .iter()
+                    .map(|p| match p {
+                        WordPart::Literal(s) | WordPart::Quoted(s) => s.clone(),
+                        WordPart::Variable { name, .. } => format!("${}", name),
+                        WordPart::Substitution { .. } => "$(...)".to_string(),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let list = parse_line("echo a&&cat x>out || echo \"a ; b\" | wc; fuzz $s &").unwrap();
+        assert_eq!(list.0.len(), 2);
+
+        let and_or = &list.0[0].and_or;
+        assert_eq!(words(&and_or.first.0[0]), ["echo", "a"]);
+        assert_eq!(and_or.rest[0].0, Connector::And);
+        assert_eq!(words(&and_or.rest[0].1 .0[0]), ["cat", "x"]);
+        assert!(matches!(&and_or.rest[0].1 .0[0], Command::Simple(c) if c.redirects.len() == 1));
+        assert_eq!(and_or.rest[1].0, Connector::Or);
+        assert_eq!(and_or.rest[1].1 .0.len(), 2);
+        assert_eq!(words(&and_or.rest[1].1 .0[0]), ["echo", "a ; b"]);
+        assert!(!list.0[0].background);
+
+        assert_eq!(words(&list.0[1].and_or.first.0[0]), ["fuzz", "$s"]);
+        assert!(list.0[1].background);
+
+        let list = parse_line("cmd 2>&1 <in &>>log").unwrap();
+        let Command::Simple(command) = &list.0[0].and_or.first.0[0] else {
+            panic!("expected a simple command");
+        };
+        let redirects = command
+            .redirects
+            .iter()
+            .map(|r| (r.fd, r.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            redirects,
+            [
+                (2, RedirectKind::Duplicate),
+                (0, RedirectKind::Read),
+                (1, RedirectKind::Append),
+                (2, RedirectKind::Duplicate),
+            ]
+        );
+
+        assert!(matches!(
+            parse_line("echo \"a"),
+            Err(ParseError::Incomplete(_))
+        ));
+        assert!(matches!(
+            parse_line("echo $(cat |"),
+            Err(ParseError::Incomplete(_))
+        ));
+        assert!(matches!(parse_line("| echo"), Err(ParseError::Invalid(_))));
+        assert!(matches!(parse_line("fi"), Err(ParseError::Invalid(e)) if e == "unexpected `fi`"));
+        assert!(matches!(
+            parse_line("if true; then echo"),
+            Err(ParseError::Incomplete(_))
+        ));
+        assert!(matches!(
+            parse_line("for 1 in a; do echo; done"),
+            Err(ParseError::Invalid(_))
+        ));
+        assert!(matches!(
+            parse_line("echo ) echo"),
+            Err(ParseError::Invalid(_))
+        ));
+        assert!(matches!(
+            parse_line("cat <<EOF\nx"),
+            Err(ParseError::Incomplete(_))
+        ));
+        assert!(matches!(parse_line("cat <<"), Err(ParseError::Invalid(_))));
+        assert!(parse_line("sleep 1 &\n").unwrap().0[0].background);
+        for background in ["sleep 1 & echo", "{ sleep 1 & }", "sleep 1 &\necho"] {
+            assert!(matches!(
+                parse_line(background),
+                Err(ParseError::Invalid(e)) if e == "`&` can only end a command line"
+            ));
+        }
+    }
+
+    #[test]
+    fn test_parse_compound() {
+        let list = parse_line(
+            "if a; then b; elif c\nthen d; else e; fi > out | wc; for q in x $y; do fuzz $q; done",
+        )
+        .unwrap();
+
+        let pipeline = &list.0[0].and_or.first.0;
+        let Command::Compound(
+            Compound::If {
+                branches,
+                otherwise,
+            },
+            redirects,
+        ) = &pipeline[0]
+        else {
+            panic!("expected an if");
+        };
+        assert_eq!(branches.len(), 2);
+        assert_eq!(words(&branches[1].1 .0[0].and_or.first.0[0]), ["d"]);
+        assert_eq!(
+            words(&otherwise.as_ref().unwrap().0[0].and_or.first.0[0]),
+            ["e"]
+        );
+        assert_eq!(redirects.len(), 1);
+        assert_eq!(words(&pipeline[1]), ["wc"]);
+
+        let Command::Compound(
+            Compound::For {
+                name,
+                words: values,
+                body,
+            },
+            _,
+        ) = &list.0[1].and_or.first.0[0]
+        else {
+            panic!("expected a for");
+        };
+        assert_eq!(name, "q");
+        assert_eq!(values.len(), 2);
+        assert_eq!(words(&body.0[0].and_or.first.0[0]), ["fuzz", "$q"]);
+
+        for definition in [
+            "f() { a; }",
+            "f () {\na\n}",
+            "function f { a; }",
+            "function f() { a; }",
+        ] {
+            let list = parse_line(definition).unwrap();
+            let Command::Function { name, body } = &list.0[0].and_or.first.0[0] else {
+                panic!("expected a function in {}", definition);
+            };
+            assert_eq!(name, "f");
+            assert_eq!(words(&body.0[0].and_or.first.0[0]), ["a"]);
+        }
+        assert!(matches!(
+            parse_line("f() {"),
+            Err(ParseError::Incomplete(_))
+        ));
+        assert!(matches!(parse_line("f() a"), Err(ParseError::Invalid(_))));
+
+        let list = parse_line("while test -e f; do echo fi done; done").unwrap();
+        let Command::Compound(Compound::While { condition, body }, _) =
+            &list.0[0].and_or.first.0[0]
+        else {
+            panic!("expected a while");
+        };
+        assert_eq!(
+            words(&condition.0[0].and_or.first.0[0]),
+            ["test", "-e", "f"]
+        );
+        assert_eq!(words(&body.0[0].and_or.first.0[0]), ["echo", "fi", "done"]);
+    }
+}
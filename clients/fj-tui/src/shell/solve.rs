@@ -0,0 +1,210 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    io::Cursor,
+    time::{Duration, Instant},
+};
+
+use crate::watch::RunResult;
+
+use super::{run_external, submit, Output, OutputMode, ShellContext};
+
+const USAGE: &str = "Usage: solve [-y] [-t <seconds>] <slug> <source-path> -- <command>";
+
+/// How long a solution can run before it's stopped.
+const DEFAULT_TIME_LIMIT: Duration = Duration::from_secs(10);
+
+/// Lines of a solution's output shown before submitting it.
+const PREVIEW_LINES: usize = 5;
+
+struct Options<'a> {
+    slug: &'a str,
+    source_path: &'a str,
+    command: &'a [String],
+    /// Submit without asking first.
+    yes: bool,
+    time_limit: Duration,
+}
+
+fn parse_options(args: &[String]) -> Option<Options<'_>> {
+    let split = args.iter().position(|arg| arg == "--")?;
+    let (args, command) = (&args[..split], &args[split + 1..]);
+    if command.is_empty() {
+        return None;
+    }
+
+    let mut yes = false;
+    let mut time_limit = DEFAULT_TIME_LIMIT;
+    let mut positional = vec![];
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-y" => yes = true,
+            "-t" => {
+                let seconds = args.next()?.parse::<f64>().ok()?;
+                time_limit = Duration::try_from_secs_f64(seconds).ok()?;
+            }
+            _ => positional.push(arg.as_str()),
+        }
+    }
+
+    let [slug, source_path] = positional[..] else {
+        return None;
+    };
+
+    Some(Options {
+        slug,
+        source_path,
+        command,
+        yes,
+        time_limit,
+    })
+}
+
+/// Fetches a problem's input, runs a solution on it, then submits the output after showing a
+/// preview.
+pub async fn solve(args: &[String], ctx: &ShellContext, mut output: Output) -> Output {
+    let Some(options) = parse_options(args) else {
+        output.eprintln(USAGE).await;
+        output.status = 1;
+        return output;
+    };
+
+    let source = match tokio::fs::read_to_string(ctx.path(options.source_path)).await {
+        Ok(source) => source,
+        Err(e) => {
+            output
+                .eprintln(&format!("{}: {}", options.source_path, e))
+                .await;
+            output.status = 1;
+            return output;
+        }
+    };
+
+    ctx.info("Fetching input...");
+    let input = match ctx.session.fuzz(options.slug).await {
+        Ok(input) => input,
+        Err(e) => {
+            ctx.report(&e);
+            output.status = 1;
+            return output;
+        }
+    };
+
+    ctx.info(&format!("Running `{}`...", options.command.join(" ")));
+    let (solution, elapsed) =
+        match run_solution(options.command, input, options.time_limit, ctx).await {
+            RunResult::Finished {
+                status: 0,
+                elapsed,
+                output,
+            } => (output, elapsed),
+            RunResult::Finished { status, .. } => {
+                output
+                    .eprintln(&format!("Solution exited with status {}.", status))
+                    .await;
+                output.status = status;
+                return output;
+            }
+            RunResult::TimedOut(limit) => {
+                output
+                    .eprintln(&format!(
+                        "Stopped after the {}s time limit.",
+                        limit.as_secs_f64()
+                    ))
+                    .await;
+                output.status = 1;
+                return output;
+            }
+        };
+
+    let lines = solution.lines().count();
+    output
+        .println(&format!(
+            "Output ({} lines, {:.2}s):",
+            lines,
+            elapsed.as_secs_f64()
+        ))
+        .await;
+    for line in solution.lines().take(PREVIEW_LINES) {
+        output.println(&format!("  {}", line)).await;
+    }
+    if lines > PREVIEW_LINES {
+        output.println("  ...").await;
+    }
+
+    let prompt = format!("Submit to {}?", options.slug);
+    if !options.yes && !ctx.confirm(&prompt).await {
+        output.println("Not submitted.").await;
+        output.status = 1;
+        return output;
+    }
+
+    submit(options.slug, &solution, &source, ctx, output).await
+}
+
+/// Runs a solution on a problem's input. Error output still goes to the console.
+pub async fn run_solution(
+    command: &[String],
+    input: String,
+    time_limit: Duration,
+    ctx: &ShellContext,
+) -> RunResult {
+    let started = Instant::now();
+    let run = run_external(
+        &command[0],
+        command[1..].to_vec(),
+        ctx,
+        Output::new(OutputMode::Piped, ctx.events.clone()),
+        Some(Box::new(Cursor::new(input))),
+    );
+
+    // Dropping the run when time's up kills the solution.
+    match tokio::time::timeout(time_limit, run).await {
+        Ok(run) => RunResult::Finished {
+            status: run.status,
+            elapsed: started.elapsed(),
+            output: run.stdout,
+        },
+        Err(_) => RunResult::TimedOut(time_limit),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split(' ').map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let full = args("-t 2.5 hello sol.py -y -- python3 sol.py");
+        let options = parse_options(&full).unwrap();
+        assert_eq!(options.slug, "hello");
+        assert_eq!(options.source_path, "sol.py");
+        assert_eq!(options.command, ["python3", "sol.py"]);
+        assert!(options.yes);
+        assert_eq!(options.time_limit, Duration::from_millis(2500));
+
+        assert!(parse_options(&args("hello sol.py")).is_none());
+        assert!(parse_options(&args("hello sol.py --")).is_none());
+        assert!(parse_options(&args("hello -- ./sol")).is_none());
+        assert!(parse_options(&args("-t x hello sol.py -- ./sol")).is_none());
+    }
+}
@@ -0,0 +1,161 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Variables kept for the rest of the session, and the `set`, `export` and `unset` builtins.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use super::{quote, Output, ShellContext, SPECIAL_VARIABLES};
+
+/// Shared by every command, so a variable set by one is seen by the next.
+pub type SharedVariables = Arc<Mutex<Variables>>;
+
+#[derive(Debug, Default, Clone)]
+pub struct Variables {
+    /// Values, and whether they're exported to external commands.
+    values: BTreeMap<String, (String, bool)>,
+    /// Status of the last pipeline, for `$?`.
+    pub status: i32,
+}
+
+impl Variables {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(|(value, _)| value.as_str())
+    }
+
+    /// Sets a variable, keeping it exported if it was.
+    pub fn set(&mut self, name: &str, value: &str) {
+        let exported = self.values.get(name).is_some_and(|(_, exported)| *exported);
+        self.values
+            .insert(name.to_string(), (value.to_string(), exported));
+    }
+
+    /// Exports a variable, setting it too if `value` is given.
+    pub fn export(&mut self, name: &str, value: Option<&str>) {
+        let entry = self
+            .values
+            .entry(name.to_string())
+            .or_insert_with(|| (String::new(), true));
+        if let Some(value) = value {
+            entry.0 = value.to_string();
+        }
+        entry.1 = true;
+    }
+
+    pub fn unset(&mut self, name: &str) {
+        self.values.remove(name);
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.values.keys()
+    }
+
+    /// Variables to pass on to external commands.
+    pub fn exported(&self) -> Vec<(String, String)> {
+        self.values
+            .iter()
+            .filter(|(_, (_, exported))| *exported)
+            .map(|(name, (value, _))| (name.clone(), value.clone()))
+            .collect()
+    }
+}
+
+/// `set [<name> [<value>...]]`: lists variables, or sets one to the rest of the arguments.
+pub async fn set(args: &[String], ctx: &ShellContext, mut output: Output) -> Output {
+    let Some((name, value)) = args.split_first() else {
+        let lines = {
+            let variables = ctx.variables.lock().unwrap();
+            variables
+                .values
+                .iter()
+                .map(|(name, (value, _))| format!("{}={}", name, quote(value)))
+                .collect::<Vec<_>>()
+        };
+        for line in lines {
+            output.println(&line).await;
+        }
+        return output;
+    };
+
+    if let Err(e) = check_name("set", name) {
+        output.eprintln(&e).await;
+        output.status = 1;
+        return output;
+    }
+
+    ctx.variables.lock().unwrap().set(name, &value.join(" "));
+    output
+}
+
+/// `export [<name>[=<value>]...]`: lists exported variables, or exports each one given.
+pub async fn export(args: &[String], ctx: &ShellContext, mut output: Output) -> Output {
+    if args.is_empty() {
+        let exported = ctx.variables.lock().unwrap().exported();
+        for (name, value) in exported {
+            output
+                .println(&format!("export {}={}", name, quote(&value)))
+                .await;
+        }
+        return output;
+    }
+
+    for arg in args {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (arg.as_str(), None),
+        };
+
+        match check_name("export", name) {
+            Ok(()) => ctx.variables.lock().unwrap().export(name, value),
+            Err(e) => {
+                output.eprintln(&e).await;
+                output.status = 1;
+            }
+        }
+    }
+
+    output
+}
+
+/// `unset <name>...`
+pub async fn unset(args: &[String], ctx: &ShellContext, mut output: Output) -> Output {
+    for name in args {
+        match check_name("unset", name) {
+            Ok(()) => ctx.variables.lock().unwrap().unset(name),
+            Err(e) => {
+                output.eprintln(&e).await;
+                output.status = 1;
+            }
+        }
+    }
+
+    output
+}
+
+fn check_name(builtin: &str, name: &str) -> Result<(), String> {
+    if SPECIAL_VARIABLES.contains(&name) {
+        Err(format!("{}: {}: read-only variable", builtin, name))
+    } else if name.is_empty()
+        || name.starts_with(|c: char| c.is_ascii_digit())
+        || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        Err(format!("{}: `{}': not a valid name", builtin, name))
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,309 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{
+    app::{Event, EventSender},
+    watch::{RunResult, WatchRun},
+};
+
+use super::{solve::run_solution, submit, Output, ShellContext};
+
+const USAGE: &str = "Usage: watch [-a] [-p <slug>] <path> [-- <command>]";
+
+/// How often watched files are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long a watched solution can run before it's stopped.
+const TIME_LIMIT: Duration = Duration::from_secs(10);
+
+/// Shortest time between automatic submissions of the same problem, so a string of quick saves
+/// can't rack up penalties.
+const SUBMIT_INTERVAL: Duration = Duration::from_secs(30);
+
+struct Options {
+    path: PathBuf,
+    /// Run instead of the changed file.
+    command: Option<Vec<String>>,
+    /// Problem to run against, instead of whichever is selected.
+    slug: Option<String>,
+    auto_submit: bool,
+}
+
+fn parse_options(args: &[String]) -> Option<Options> {
+    let (args, command) = match args.iter().position(|arg| arg == "--") {
+        Some(split) if split + 1 < args.len() => (&args[..split], Some(args[split + 1..].to_vec())),
+        Some(_) => return None,
+        None => (args, None),
+    };
+
+    let mut auto_submit = false;
+    let mut slug = None;
+    let mut path = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-a" => auto_submit = true,
+            "-p" => slug = Some(args.next()?.clone()),
+            _ if path.is_none() => path = Some(PathBuf::from(arg)),
+            _ => return None,
+        }
+    }
+
+    Some(Options {
+        path: path?,
+        command,
+        slug,
+        auto_submit,
+    })
+}
+
+/// Clears the watch pane when watching stops, including when the job is killed.
+struct ClearPane(EventSender);
+
+impl Drop for ClearPane {
+    fn drop(&mut self) {
+        let _ = self.0.send(Event::Watch(None));
+    }
+}
+
+/// What `watch -a` has submitted for each problem. Shared by every command, so restarting `watch`
+/// or running two at once can't get round the wait between submissions.
+pub type SharedAutoSubmissions = Arc<Mutex<HashMap<String, Submissions>>>;
+
+/// What has been submitted automatically for a problem.
+#[derive(Debug, Default)]
+pub struct Submissions {
+    last: Option<(Instant, String)>,
+    accepted: bool,
+}
+
+/// Re-runs a solution whenever it's saved, against the selected problem's input. Runs until the
+/// job is killed.
+pub async fn watch(args: &[String], ctx: &ShellContext, mut output: Output) -> Output {
+    let Some(options) = parse_options(args) else {
+        output.eprintln(USAGE).await;
+        output.status = 1;
+        return output;
+    };
+
+    let root = ctx.path(&options.path);
+    let mut times = match modified_times(&root).await {
+        Ok(times) => times,
+        Err(e) => {
+            output
+                .eprintln(&format!("{}: {}", options.path.display(), e))
+                .await;
+            output.status = 1;
+            return output;
+        }
+    };
+
+    output
+        .println(&format!(
+            "Watching {}. Stop with Ctrl-C, or `kill` if it's in the background.",
+            options.path.display()
+        ))
+        .await;
+
+    let _pane = ClearPane(ctx.events.clone());
+    let mut inputs = HashMap::<String, String>::new();
+
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+
+        // The file may be halfway through being replaced.
+        let Ok(new_times) = modified_times(&root).await else {
+            continue;
+        };
+        let changed = new_times
+            .iter()
+            .filter(|(path, time)| times.get(*path) != Some(time))
+            .max_by_key(|(_, time)| **time)
+            .map(|(path, _)| path.clone());
+        times = new_times;
+
+        let Some(file) = changed else {
+            continue;
+        };
+
+        let slug = match &options.slug {
+            Some(slug) => Some(slug.clone()),
+            None => ctx.selected_problem().await,
+        };
+        let Some(slug) = slug else {
+            output.eprintln("watch: no problem is selected").await;
+            continue;
+        };
+
+        let input = match inputs.get(&slug) {
+            Some(input) => input.clone(),
+            None => match ctx.session.fuzz(&slug).await {
+                Ok(input) => inputs.entry(slug.clone()).or_insert(input).clone(),
+                Err(e) => {
+                    ctx.report(&e);
+                    continue;
+                }
+            },
+        };
+
+        let mut run = WatchRun {
+            file: file
+                .strip_prefix(ctx.cwd())
+                .unwrap_or(&file)
+                .display()
+                .to_string(),
+            slug: slug.clone(),
+            result: None,
+            submission: None,
+        };
+        ctx.send(Event::Watch(Some(run.clone())));
+
+        let command = options
+            .command
+            .clone()
+            .unwrap_or_else(|| vec![command_for(&file)]);
+        let result = run_solution(&command, input, TIME_LIMIT, ctx).await;
+        run.result = Some(result.clone());
+        ctx.send(Event::Watch(Some(run.clone())));
+
+        let RunResult::Finished {
+            status: 0,
+            output: solution,
+            ..
+        } = result
+        else {
+            continue;
+        };
+        if !options.auto_submit {
+            continue;
+        }
+
+        let source = match tokio::fs::read_to_string(&file).await {
+            Ok(source) => source,
+            Err(e) => {
+                output.eprintln(&format!("{}: {}", file.display(), e)).await;
+                continue;
+            }
+        };
+
+        let skipped = {
+            let mut submissions = ctx.auto_submissions.lock().unwrap();
+            let submitted = submissions.entry(slug.clone()).or_default();
+            let skipped = match &submitted.last {
+                _ if submitted.accepted => Some("already accepted".to_string()),
+                Some((_, last)) if *last == solution => {
+                    Some("same output as last time".to_string())
+                }
+                Some((at, _)) if at.elapsed() < SUBMIT_INTERVAL => Some(format!(
+                    "next submission allowed in {}s",
+                    (SUBMIT_INTERVAL - at.elapsed()).as_secs() + 1
+                )),
+                _ => None,
+            };
+            // Claimed before submitting, so another `watch` can't submit at the same time.
+            if skipped.is_none() {
+                submitted.last = Some((Instant::now(), solution.clone()));
+            }
+            skipped
+        };
+
+        if let Some(reason) = skipped {
+            run.submission = Some(format!("Not submitted: {}", reason));
+            ctx.send(Event::Watch(Some(run)));
+            continue;
+        }
+
+        output = submit(&slug, &solution, &source, ctx, output).await;
+        let accepted = output.status == 0;
+        if accepted {
+            let mut submissions = ctx.auto_submissions.lock().unwrap();
+            submissions.entry(slug.clone()).or_default().accepted = true;
+        }
+
+        run.submission = Some(if accepted {
+            "Accepted".to_string()
+        } else {
+            "Not accepted, see the console".to_string()
+        });
+        ctx.send(Event::Watch(Some(run)));
+        output.status = 0;
+    }
+}
+
+/// How to run a changed file when no command was given.
+fn command_for(file: &Path) -> String {
+    if file.is_relative() && file.components().count() == 1 {
+        format!("./{}", file.display())
+    } else {
+        file.display().to_string()
+    }
+}
+
+/// Modification times of a file, or of the files in a directory. Hidden files, such as editors'
+/// swap files, are skipped.
+async fn modified_times(path: &Path) -> std::io::Result<HashMap<PathBuf, SystemTime>> {
+    let mut times = HashMap::new();
+
+    let metadata = tokio::fs::metadata(path).await?;
+    if !metadata.is_dir() {
+        times.insert(path.to_path_buf(), metadata.modified()?);
+        return Ok(times);
+    }
+
+    let mut entries = tokio::fs::read_dir(path).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        let metadata = entry.metadata().await?;
+        if !hidden && metadata.is_file() {
+            times.insert(entry.path(), metadata.modified()?);
+        }
+    }
+
+    Ok(times)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split(' ').map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let options = parse_options(&args("-a src -p hello -- python3 sol.py")).unwrap();
+        assert_eq!(options.path, PathBuf::from("src"));
+        assert_eq!(options.slug.as_deref(), Some("hello"));
+        assert_eq!(options.command.unwrap(), ["python3", "sol.py"]);
+        assert!(options.auto_submit);
+
+        let options = parse_options(&args("sol")).unwrap();
+        assert_eq!(options.command, None);
+        assert!(!options.auto_submit);
+
+        assert!(parse_options(&args("a b")).is_none());
+        assert!(parse_options(&args("sol --")).is_none());
+        assert!(parse_options(&args("-p")).is_none());
+    }
+}
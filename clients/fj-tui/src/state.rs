@@ -13,40 +13,304 @@
  * with this program. If not, see <https://www.gnu.org/licenses/>.
  */
 
-use std::{future::Future, pin::Pin, sync::Arc};
+use std::sync::Arc;
 
-use ratatui::widgets::ListState;
-use tokio::sync::Mutex;
+use fj_client::{
+    Broadcast, Clock, ClockState, Competition, ConnectionStatus, Error, Problem, ScoreboardRow,
+    Session, Submission,
+};
+use ratatui::{crossterm, widgets::ListState};
 
 use crate::{
-    api, auth, clock::Clock, console::ConsoleState, key::KeyState, problem::Problem, scroll::Scroll,
+    app::{self, Effect, Event},
+    console::ConsoleState,
+    job::Jobs,
+    key::{self, KeyState},
+    scroll::Scroll,
+    shell,
+    toast::Toast,
+    view::View,
+    watch::WatchRun,
 };
 
 pub struct AppState {
     pub problems: Vec<Problem>,
-    pub session: api::Session,
+    /// Shared with the tasks making network requests.
+    pub session: Arc<Session>,
     pub running: bool,
     pub instructions_scroll: Scroll,
     pub console: ConsoleState,
     pub key: KeyState,
     pub clock: Option<Clock>,
-    /// Commands run when a new problem is added. `$q` assigned to slug.
-    pub on_new_problem: Vec<String>,
+    pub connection: ConnectionStatus,
+    /// Rows ordered by rank, replaced whenever the server pushes an update.
+    pub scoreboard: Vec<ScoreboardRow>,
+    pub scoreboard_scroll: Scroll,
+    pub view: View,
+    /// Once the user picks a view we stop switching it for them.
+    view_chosen: bool,
+    pub competition: Option<Competition>,
+    pub competition_scroll: Scroll,
+    /// Announcements in the order they were received.
+    pub broadcasts: Vec<Broadcast>,
+    pub broadcasts_scroll: Scroll,
+    /// Broadcasts received since the announcements view was last opened.
+    pub unread_broadcasts: usize,
+    pub toast: Option<Toast>,
+    /// Our submissions for `submissions_slug`, newest first.
+    pub submissions: Vec<Submission>,
+    pub submissions_slug: Option<String>,
+    /// Set if the last refresh of `submissions` failed.
+    pub submissions_error: Option<String>,
+    pub selected_submission: ListState,
+    pub submission_scroll: Scroll,
+    pub jobs: Jobs,
+    /// Shown in the watch pane while a solution is being watched.
+    pub watch: Option<WatchRun>,
+    /// Variables set with `set` and `export`.
+    pub variables: shell::SharedVariables,
+    /// Aliases and functions, including the hook run for new problems.
+    pub definitions: shell::SharedDefinitions,
+    /// The console's working directory, changed with `cd`.
+    pub directories: shell::SharedDirectories,
+    /// What `watch -a` has submitted, kept for the whole session.
+    pub auto_submissions: shell::SharedAutoSubmissions,
     selected_problem: ListState,
+    /// Set when the problems couldn't be fetched because the competition hadn't started.
+    problems_pending: bool,
 }
 
 impl AppState {
-    pub fn new(session: api::Session) -> Self {
+    pub fn new(session: Session) -> Self {
         Self {
             problems: vec![],
-            session,
+            session: Arc::new(session),
             running: true,
             selected_problem: ListState::default(),
             instructions_scroll: Scroll::new(),
             console: ConsoleState::default(),
             key: KeyState::default(),
-            on_new_problem: vec![],
+            jobs: Jobs::default(),
+            variables: shell::SharedVariables::default(),
+            definitions: shell::SharedDefinitions::default(),
+            directories: shell::SharedDirectories::default(),
+            auto_submissions: shell::SharedAutoSubmissions::default(),
+            watch: None,
             clock: None,
+            connection: ConnectionStatus::default(),
+            scoreboard: vec![],
+            scoreboard_scroll: Scroll::new(),
+            view: View::default(),
+            view_chosen: false,
+            competition: None,
+            competition_scroll: Scroll::new(),
+            broadcasts: vec![],
+            broadcasts_scroll: Scroll::new(),
+            unread_broadcasts: 0,
+            toast: None,
+            submissions: vec![],
+            submissions_slug: None,
+            submissions_error: None,
+            selected_submission: ListState::default(),
+            submission_scroll: Scroll::new(),
+            problems_pending: false,
+        }
+    }
+
+    /// Applies an event, returning any work that needs to be started because of it.
+    pub fn update(&mut self, event: Event) -> Vec<Effect> {
+        match event {
+            Event::Terminal(crossterm::event::Event::Key(key)) => {
+                return key::handle_press(self, key);
+            }
+            Event::Terminal(crossterm::event::Event::Paste(text)) => key::paste(self, &text),
+            Event::Terminal(_) => {}
+            Event::Tick => return self.fetch_problems_if_started(),
+            Event::Clock(clock) => {
+                self.clock = Some(clock);
+                return self.fetch_problems_if_started();
+            }
+            Event::Scoreboard(rows) => self.scoreboard = rows,
+            Event::Broadcast(broadcast) => {
+                if self.broadcasts.iter().any(|b| b.id == broadcast.id) {
+                    return vec![];
+                }
+
+                if self.view != View::Announcements {
+                    self.unread_broadcasts += 1;
+                }
+
+                self.toast = Some(Toast::new(&format!("📢 {}", broadcast.title)));
+                self.broadcasts.push(broadcast);
+            }
+            Event::Connection(status) => {
+                if self.connection == ConnectionStatus::Connected
+                    && status != ConnectionStatus::Connected
+                {
+                    self.console
+                        .eprintln("Lost connection to server. Reconnecting...");
+                }
+                self.connection = status;
+            }
+            Event::Problems(Err(Error::NotRunning)) => {
+                self.console.println(
+                    "The competition hasn't started yet. Problems will appear once it does.",
+                );
+                self.problems_pending = true;
+            }
+            Event::Problems(Err(e)) => {
+                return self.update(match app::error_event(&e) {
+                    Event::EPrint(message) => {
+                        Event::EPrint(format!("Failed to fetch problems: {}", message))
+                    }
+                    event => event,
+                });
+            }
+            Event::Problems(Ok(problems)) => {
+                let mut effects = vec![];
+
+                let hook = shell::NEW_PROBLEM_HOOK;
+                if self.definitions.lock().unwrap().function(hook).is_some() {
+                    let command = format!("{} \"$q\"", hook);
+                    for problem in &problems {
+                        let mut env = shell::Env::default();
+                        env.insert("q".to_string(), problem.slug.clone());
+
+                        effects.push(Effect::Exec {
+                            job: self.jobs.add(&command, true),
+                            command: command.clone(),
+                            env,
+                            output_mode: shell::OutputMode::Piped,
+                        });
+                    }
+                }
+
+                self.problems = problems;
+                self.selected_problem_borrow_mut().select(Some(0));
+
+                return effects;
+            }
+            Event::Competition(competition) => self.competition = Some(competition),
+            Event::Submissions { slug, result } => {
+                match result {
+                    Ok(submissions) => {
+                        self.submissions = submissions;
+                        self.submissions_error = None;
+                    }
+                    Err(e) => {
+                        self.submissions.clear();
+                        self.submissions_error = Some(e);
+                    }
+                }
+
+                self.submissions_slug = Some(slug);
+                let selected = if self.submissions.is_empty() {
+                    None
+                } else {
+                    Some(0)
+                };
+                self.selected_submission.select(selected);
+                self.submission_scroll.set_position(0);
+            }
+            // Other problems' submissions are fetched when they're selected.
+            Event::Judged(slug) => {
+                if self.selected_problem().is_some_and(|p| p.slug == slug) {
+                    return vec![Effect::RefreshSubmissions(slug)];
+                }
+            }
+            Event::Print(message) | Event::Info(message) => self.console.println(&message),
+            Event::EPrint(message) => self.console.eprintln(&message),
+            Event::Stderr(message) => self.console.print_stderr(&message),
+            Event::ClearConsole => self.console.clear(),
+            Event::Confirm(confirm) => {
+                // Only one question can be answered at a time.
+                if let Some(previous) = self.console.confirm.replace(confirm) {
+                    previous.answer(false);
+                }
+                self.console.scroll.to_bottom();
+            }
+            Event::SelectedProblem(reply) => {
+                let _ = reply.send(self.selected_problem().map(|p| p.slug.clone()));
+            }
+            Event::Watch(run) => self.watch = run,
+            Event::JobDone(id) => {
+                if let Some(job) = self.jobs.finish(id).filter(|job| job.background) {
+                    self.console
+                        .println(&format!("[{}] Done  {}", job.id, job.command));
+                }
+            }
+            Event::Foreground(id) => self.jobs.set_background(id, false),
+            Event::Kill(id) => {
+                if let Some(job) = self.jobs.kill(id) {
+                    self.console
+                        .println(&format!("[{}] Killed  {}", job.id, job.command));
+                }
+            }
+            Event::Quit => self.running = false,
+        }
+
+        vec![]
+    }
+
+    /// Effect to fetch the problems if we were waiting for the competition to start and it has.
+    fn fetch_problems_if_started(&mut self) -> Vec<Effect> {
+        let started = self
+            .clock
+            .as_ref()
+            .is_some_and(|c| c.state() != ClockState::Before);
+
+        if self.problems_pending && started {
+            self.problems_pending = false;
+            vec![Effect::FetchProblems]
+        } else {
+            vec![]
+        }
+    }
+
+    /// Effect to re-fetch our submissions for the selected problem.
+    pub fn refresh_submissions(&self) -> Vec<Effect> {
+        self.selected_problem()
+            .map(|p| Effect::RefreshSubmissions(p.slug.clone()))
+            .into_iter()
+            .collect()
+    }
+
+    pub fn selected_problem(&self) -> Option<&Problem> {
+        self.selected_problem
+            .selected()
+            .and_then(|s| self.problems.get(s))
+    }
+
+    /// Switches the main view, marking announcements as read when they're opened.
+    pub fn set_view(&mut self, view: View) {
+        if view == View::Announcements {
+            self.unread_broadcasts = 0;
+        }
+        self.view = view;
+        self.view_chosen = true;
+    }
+
+    /// Shows the competition overview until the clock starts, then the problems, unless the
+    /// user has already chosen a view.
+    pub fn update_default_view(&mut self) {
+        if self.view_chosen {
+            return;
+        }
+
+        self.view = match self.clock.as_ref().map(|c| c.state()) {
+            Some(ClockState::Before) | None => View::Competition,
+            Some(_) => View::Problems,
+        };
+    }
+
+    /// Scroll of whichever view is currently shown.
+    pub fn view_scroll_mut(&mut self) -> &mut Scroll {
+        match self.view {
+            View::Competition => &mut self.competition_scroll,
+            View::Problems => &mut self.instructions_scroll,
+            View::Submissions => &mut self.submission_scroll,
+            View::Scoreboard => &mut self.scoreboard_scroll,
+            View::Announcements => &mut self.broadcasts_scroll,
         }
     }
 
@@ -65,100 +329,118 @@ impl AppState {
     }
 }
 
-#[derive(Clone)]
-pub struct AppStateMutex {
-    rt: Arc<std::sync::Mutex<tokio::runtime::Runtime>>,
-    app_state: Arc<tokio::sync::Mutex<AppState>>,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-trait AsyncCallback: FnOnce(Arc<Mutex<AppState>>) -> Pin<Box<dyn Future<Output = ()> + Send>> {}
-impl<T> AsyncCallback for T where
-    T: FnOnce(Arc<Mutex<AppState>>) -> Pin<Box<dyn Future<Output = ()> + Send>>
-{
-}
+    fn problem(slug: &str) -> Problem {
+        Problem {
+            slug: slug.to_string(),
+            ..Default::default()
+        }
+    }
 
-fn force_boxed<T, A>(f: fn(Arc<Mutex<AppState>>, A) -> T, a: A) -> impl AsyncCallback
-where
-    T: Future<Output = ()> + 'static + Send,
-    A: Clone + Send + 'static,
-{
-    move |n| Box::pin(f(n, a)) as _
-}
+    #[tokio::test]
+    async fn test_new_problems() {
+        let mut app_state = AppState::new(Session::new_no_connection());
+        let (events, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let ctx = app::shell_context(&app_state, &events);
+        let env = shell::Env::default();
+        shell::exec(
+            "on_new_problem() { fuzz $1; }",
+            &ctx,
+            shell::OutputMode::Piped,
+            &env,
+        )
+        .await;
+
+        let effects = app_state.update(Event::Problems(Ok(vec![problem("a"), problem("b")])));
 
-impl AppStateMutex {
-    pub fn new(server: String, creds: auth::Credentials) -> Self {
-        let rt = Arc::new(std::sync::Mutex::new(
-            tokio::runtime::Runtime::new().unwrap(),
-        ));
+        let slugs = effects
+            .iter()
+            .map(|e| match e {
+                Effect::Exec { command, env, .. } => {
+                    assert_eq!(command, "on_new_problem \"$q\"");
+                    env["q"].clone()
+                }
+                _ => panic!("unexpected effect"),
+            })
+            .collect::<Vec<String>>();
 
-        let sess = rt
-            .lock()
+        assert_eq!(slugs, ["a", "b"]);
+        assert_eq!(app_state.selected_problem().unwrap().slug, "a");
+        assert_eq!(
+            app_state.update(Event::Judged("a".to_string())),
+            [Effect::RefreshSubmissions("a".to_string())]
+        );
+        assert!(app_state.update(Event::Judged("b".to_string())).is_empty());
+    }
+
+    #[test]
+    fn test_problems_before_start() {
+        let mut app_state = AppState::new(Session::new_no_connection());
+        let clock = |start: i64| Clock {
+            start: chrono::Utc::now() + chrono::Duration::minutes(start),
+            finish: chrono::Utc::now() + chrono::Duration::hours(1),
+        };
+
+        app_state.update(Event::Problems(Err(Error::NotRunning)));
+        assert!(app_state
+            .console
+            .messages
+            .last()
             .unwrap()
-            .block_on(async { api::Session::new(server, creds.clone()).await });
+            .text
+            .contains("hasn't started"));
 
-        let sess = match sess {
-            Ok(s) => s,
-            Err(e) => {
-                eprintln!("Connection Error: {}", e);
-                std::process::exit(1);
-            }
+        assert_eq!(app_state.update(Event::Clock(clock(5))), []);
+        assert_eq!(
+            app_state.update(Event::Clock(clock(-1))),
+            [Effect::FetchProblems]
+        );
+        assert_eq!(app_state.update(Event::Tick), []);
+    }
+
+    #[test]
+    fn test_multi_line_input() {
+        use crossterm::event::{Event as Terminal, KeyCode, KeyEvent, KeyModifiers};
+
+        let mut app_state = AppState::new(Session::new_no_connection());
+        let key = |app_state: &mut AppState, code, modifiers| {
+            app_state.update(Event::Terminal(Terminal::Key(KeyEvent::new(
+                code, modifiers,
+            ))))
         };
 
-        let app_state = Arc::new(tokio::sync::Mutex::new(AppState::new(sess)));
-        Self { rt, app_state }
-    }
-
-    /// Print utility to avoid having to deal with the locking and callbacks.
-    pub fn println(&self, message: &str) {
-        self.run_sync(move |mut app_state| {
-            app_state.console.println(message);
-        });
-    }
-
-    /// Execute a synchronous function with access to the app state.
-    pub fn run_sync<F, T>(&self, f: F) -> T
-    where
-        F: FnOnce(tokio::sync::MutexGuard<'_, AppState>) -> T,
-    {
-        let rt = self.rt.lock().unwrap();
-        rt.block_on(async {
-            let app_state = self.app_state.lock().await;
-            f(app_state)
-        })
-    }
-
-    /// Execute an async function with access to the app state mutex. It needs to be like this
-    /// because we don't have async closures yet.
-    ///
-    /// # Arguments
-    /// * `f` - The function to run. Function should be a pointer to an async function that
-    /// takes the mutex-guarded app state and any data you want to pass through.
-    /// * `a` - The data to pass through to the function.
-    ///
-    /// # Example
-    /// ```rust
-    /// async fn add(app_state: Arc<Mutex<AppState>>, (a, b): (i32, i32)) {
-    ///    let mut app_state = app_state.lock().await;
-    ///    app_state.console.println(&format!("{} + {} = {}", a, b, a + b));
-    /// }
-    ///
-    /// ...
-    ///
-    /// app_state.run_async(add, (1, 2));
-    /// ```
-    pub fn run_async<T, A>(&self, f: fn(Arc<Mutex<AppState>>, A) -> T, a: A)
-    where
-        T: Future<Output = ()> + 'static + Send,
-        A: Clone + Send + 'static,
-    {
-        self.__run_async(force_boxed(f, a));
-    }
-
-    fn __run_async(&self, f: impl AsyncCallback + Send + 'static) {
-        let app_state = self.app_state.clone();
-        let rt = self.rt.lock().unwrap();
-        rt.spawn(async move {
-            let _ = f(app_state).await;
-        });
+        app_state.update(Event::Terminal(Terminal::Paste("cat <<EOF".to_string())));
+        assert_eq!(key(&mut app_state, KeyCode::Enter, KeyModifiers::NONE), []);
+        key(&mut app_state, KeyCode::Char('x'), KeyModifiers::NONE);
+        assert_eq!(key(&mut app_state, KeyCode::Enter, KeyModifiers::ALT), []);
+        app_state.update(Event::Terminal(Terminal::Paste("y\nEOF".to_string())));
+
+        match &key(&mut app_state, KeyCode::Enter, KeyModifiers::NONE)[..] {
+            [Effect::Exec { command, .. }] => assert_eq!(command, "cat <<EOF\nx\ny\nEOF"),
+            effects => panic!("unexpected effects: {:?}", effects),
+        }
+    }
+
+    #[test]
+    fn test_broadcasts() {
+        let mut app_state = AppState::new(Session::new_no_connection());
+        let broadcast = Broadcast {
+            id: "1".to_string(),
+            title: "Correction".to_string(),
+            content: "Problem 2 is fixed.".to_string(),
+        };
+
+        app_state.update(Event::Broadcast(broadcast.clone()));
+        app_state.update(Event::Broadcast(broadcast));
+
+        assert_eq!(app_state.broadcasts.len(), 1);
+        assert_eq!(app_state.unread_broadcasts, 1);
+        assert!(app_state.toast.is_some());
+
+        app_state.set_view(View::Announcements);
+        assert_eq!(app_state.unread_broadcasts, 0);
     }
 }
@@ -0,0 +1,38 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::time::{Duration, Instant};
+
+/// How long a toast stays on screen.
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+
+/// Short-lived notification drawn over the top-right of the screen.
+pub struct Toast {
+    pub message: String,
+    created: Instant,
+}
+
+impl Toast {
+    pub fn new(message: &str) -> Self {
+        Self {
+            message: message.to_string(),
+            created: Instant::now(),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.created.elapsed() >= TOAST_DURATION
+    }
+}
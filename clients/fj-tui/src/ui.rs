@@ -15,25 +15,29 @@
 
 use ratatui::Frame;
 
+use fj_client::{ClockState, ConnectionStatus, ProblemScore};
+
 use crate::{
-    clock::{self, ClockState},
-    md,
-    utils::number_of_lines_when_broken,
-    AppState,
+    console::MessageKind, line_editor::LineEditor, md, scroll::Scroll, shell,
+    utils::number_of_lines_when_broken, view::View, watch::RunResult, AppState,
 };
 
 use ratatui::{
-    layout::{Constraint, Direction, Layout, Margin},
+    layout::{Constraint, Direction, Layout, Margin, Rect},
     style::{palette::tailwind::SLATE, Modifier, Style, Stylize},
     symbols::scrollbar,
     text::{Line, Span},
     widgets::{
-        Block, Borders, HighlightSpacing, List, ListItem, Paragraph, Scrollbar,
-        ScrollbarOrientation, Wrap,
+        Block, Borders, Cell, Clear, HighlightSpacing, List, ListItem, ListState, Paragraph, Row,
+        Scrollbar, ScrollbarOrientation, Table, Tabs, Wrap,
     },
 };
 
-pub fn draw(frame: &mut Frame, mut app_state: tokio::sync::MutexGuard<AppState>) {
+const SELECTED_STYLE: Style = Style::new().bg(SLATE.c400).add_modifier(Modifier::BOLD);
+
+pub fn draw(frame: &mut Frame, app_state: &mut AppState) {
+    app_state.update_default_view();
+
     let main_layout = Layout::new(
         Direction::Vertical,
         [Constraint::Length(3), Constraint::Min(0)],
@@ -56,41 +60,97 @@ pub fn draw(frame: &mut Frame, mut app_state: tokio::sync::MutexGuard<AppState>)
     )
     .split(inner_layout[1]);
 
-    let instructions_area = question_area[0];
-    let console_area = question_area[1];
+    let view_area = question_area[0];
+    let mut console_area = question_area[1];
+
+    // The watch pane takes half of the console's space while there's something to show.
+    if app_state.watch.is_some() {
+        let bottom_layout = Layout::new(
+            Direction::Horizontal,
+            [Constraint::Percentage(50), Constraint::Percentage(50)],
+        )
+        .split(console_area);
+        console_area = bottom_layout[0];
+        watch_pane(app_state, frame, bottom_layout[1]);
+    }
 
-    top_bar(&app_state, frame, top_bar_area);
-    question_list(&mut app_state, frame, question_list_area);
-    instructions(&mut app_state, question_area, frame, instructions_area);
+    top_bar(app_state, frame, top_bar_area);
+    question_list(app_state, frame, question_list_area);
+    match app_state.view {
+        View::Competition => competition(app_state, frame, view_area),
+        View::Problems => instructions(app_state, question_area, frame, view_area),
+        View::Submissions => submissions(app_state, frame, view_area),
+        View::Scoreboard => scoreboard(app_state, frame, view_area),
+        View::Announcements => announcements(app_state, frame, view_area),
+    }
+    toast(app_state, frame);
     console(app_state, console_area, frame);
+    completion_popup(app_state, frame, console_area);
 }
 
-fn top_bar(
-    app_state: &tokio::sync::MutexGuard<AppState>,
-    frame: &mut Frame,
-    top_bar_area: ratatui::prelude::Rect,
-) {
+fn top_bar(app_state: &AppState, frame: &mut Frame, top_bar_area: ratatui::prelude::Rect) {
+    // Each tab is padded by a space either side and separated by a divider.
+    let tabs_width = View::ALL.iter().map(|v| v.title().len() + 3).sum::<usize>() + 1;
+
+    let layout = Layout::new(
+        Direction::Horizontal,
+        [Constraint::Min(0), Constraint::Length(tabs_width as u16)],
+    )
+    .split(top_bar_area);
+
     let mut top_bar_text = vec![];
 
-    top_bar_text.extend(vec![
-        "Logged in as ".into(),
-        app_state.session.creds.username.clone().italic(),
-        ".".into(),
-    ]);
+    if let Some(competition) = &app_state.competition {
+        top_bar_text.extend(vec![
+            competition.name.clone().bold(),
+            " | ".into(),
+            app_state.session.creds.username.clone().italic(),
+        ]);
+    } else {
+        top_bar_text.extend(vec![
+            "Logged in as ".into(),
+            app_state.session.creds.username.clone().italic(),
+            ".".into(),
+        ]);
+    }
 
     if let Some(clock) = &app_state.clock {
         top_bar_text.push(" | ".into());
         top_bar_text.push(clock.countdown_string().into());
     }
 
+    top_bar_text.push(" | ".into());
+    top_bar_text.push(connection_label(app_state.connection));
+
+    if app_state.jobs.len() > 0 {
+        top_bar_text.push(" | ".into());
+        top_bar_text.push(format!("⚙ {} running", app_state.jobs.len()).cyan());
+    }
+
+    if app_state.unread_broadcasts > 0 {
+        top_bar_text.push(" | ".into());
+        top_bar_text.push(
+            format!("📢 {} unread", app_state.unread_broadcasts)
+                .yellow()
+                .bold(),
+        );
+    }
+
     frame.render_widget(
         Paragraph::new(Line::from(top_bar_text)).block(Block::bordered()),
-        top_bar_area,
+        layout[0],
     );
+
+    let tabs = Tabs::new(View::ALL.iter().map(|v| v.title()))
+        .select(app_state.view.index())
+        .highlight_style(SELECTED_STYLE)
+        .block(Block::bordered());
+
+    frame.render_widget(tabs, layout[1]);
 }
 
 fn question_list(
-    app_state: &mut tokio::sync::MutexGuard<AppState>,
+    app_state: &mut AppState,
     frame: &mut Frame,
     question_list_area: ratatui::prelude::Rect,
 ) {
@@ -106,8 +166,6 @@ fn question_list(
         })
         .collect();
 
-    const SELECTED_STYLE: Style = Style::new().bg(SLATE.c400).add_modifier(Modifier::BOLD);
-
     let list = List::new(items)
         .block(block)
         .highlight_style(SELECTED_STYLE)
@@ -122,7 +180,7 @@ fn question_list(
 }
 
 fn instructions(
-    app_state: &mut tokio::sync::MutexGuard<AppState>,
+    app_state: &mut AppState,
     question_area: std::rc::Rc<[ratatui::prelude::Rect]>,
     frame: &mut Frame,
     instructions_area: ratatui::prelude::Rect,
@@ -135,7 +193,12 @@ fn instructions(
                 app_state.problems[s].difficulty,
                 app_state.problems[s].points,
             ),
-            None => ("".to_string(), Some("No Question Selected".to_string()), 0, 0),
+            None => (
+                "".to_string(),
+                Some("No Question Selected".to_string()),
+                0,
+                0,
+            ),
         };
 
     let mut contents: Vec<Line> = vec![];
@@ -143,10 +206,14 @@ fn instructions(
     let clock_state = if let Some(clock) = &app_state.clock {
         clock.state()
     } else {
-        clock::ClockState::During
+        ClockState::During
     };
 
-    let md = markdown::to_mdast(md_source.as_deref().unwrap_or(""), &markdown::ParseOptions::default()).unwrap();
+    let md = markdown::to_mdast(
+        md_source.as_deref().unwrap_or(""),
+        &markdown::ParseOptions::default(),
+    )
+    .unwrap();
 
     match clock_state {
         ClockState::During => {
@@ -166,6 +233,9 @@ fn instructions(
         }
         ClockState::Before => {
             contents.push(Line::from("The competition has not started yet.".bold()));
+            contents.push(Line::from(
+                "See the Competition view for the runsheet and rules.".italic(),
+            ));
         }
         ClockState::After => {
             contents.push(Line::from("The competition has ended.".bold()));
@@ -204,34 +274,407 @@ fn instructions(
     );
 }
 
-fn console(
-    mut app_state: tokio::sync::MutexGuard<AppState>,
-    console_area: ratatui::prelude::Rect,
+fn competition(app_state: &mut AppState, frame: &mut Frame, competition_area: Rect) {
+    let Some(competition) = app_state.competition.clone() else {
+        frame.render_widget(
+            Paragraph::new("Loading...".italic()).block(Block::bordered().title("Competition")),
+            competition_area,
+        );
+        return;
+    };
+
+    let md = markdown::to_mdast(
+        &competition.instructions,
+        &markdown::ParseOptions::default(),
+    )
+    .unwrap();
+
+    let mut contents: Vec<Line> = vec![];
+    md::render(&md, &mut contents);
+
+    scrolled_paragraph(
+        frame,
+        competition_area,
+        contents,
+        &mut app_state.competition_scroll,
+        Block::bordered().title(competition.name),
+    );
+}
+
+fn submissions(app_state: &mut AppState, frame: &mut Frame, submissions_area: Rect) {
+    let Some(problem) = app_state.selected_problem() else {
+        frame.render_widget(
+            Paragraph::new("No Question Selected").block(Block::bordered().title("Submissions")),
+            submissions_area,
+        );
+        return;
+    };
+
+    let title = format!("Submissions: {} {}", problem.icon, problem.title);
+    let slug = problem.slug.clone();
+
+    if app_state.submissions_slug.as_ref() != Some(&slug) {
+        frame.render_widget(
+            Paragraph::new("Loading...".italic()).block(Block::bordered().title(title)),
+            submissions_area,
+        );
+        return;
+    }
+
+    if let Some(error) = &app_state.submissions_error {
+        frame.render_widget(
+            Paragraph::new(error.clone().red())
+                .wrap(Wrap { trim: false })
+                .block(Block::bordered().title(title)),
+            submissions_area,
+        );
+        return;
+    }
+
+    if app_state.submissions.is_empty() {
+        frame.render_widget(
+            Paragraph::new("No submissions yet.".italic()).block(Block::bordered().title(title)),
+            submissions_area,
+        );
+        return;
+    }
+
+    let layout = Layout::new(
+        Direction::Horizontal,
+        [Constraint::Length(32), Constraint::Min(0)],
+    )
+    .split(submissions_area);
+
+    let count = app_state.submissions.len();
+    let items: Vec<ListItem> = app_state
+        .submissions
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            let verdict = verdict_label(s.ok, s.verdict());
+            let time = s.time.with_timezone(&chrono::Local).format("%H:%M:%S");
+            let heading = Line::from(vec![format!("#{} ", count - i).into(), verdict]);
+            let details = Line::from(format!("    {}", time).dark_gray());
+            ListItem::from(vec![heading, details])
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(Block::bordered().title(title))
+        .highlight_style(SELECTED_STYLE)
+        .highlight_symbol(">")
+        .highlight_spacing(HighlightSpacing::Always);
+
+    frame.render_stateful_widget(list, layout[0], &mut app_state.selected_submission);
+
+    let selected = app_state.selected_submission.selected().unwrap_or(0);
+    let submission = app_state.submissions[selected.min(count - 1)].clone();
+
+    let mut contents: Vec<Line> = vec![
+        Line::from(vec![
+            "Verdict: ".into(),
+            verdict_label(submission.ok, submission.verdict()),
+        ]),
+        Line::from(format!(
+            "Submitted: {}",
+            submission
+                .time
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+        )),
+        Line::from(format!("ID: {}", submission.id).dark_gray()),
+    ];
+
+    if let Some(vlms) = submission.vlms {
+        contents.push(Line::from(format!("Judge time: {:.0} ms", vlms)));
+    }
+
+    if let Some(vler) = submission.vler.filter(|e| !e.is_empty()) {
+        contents.push(Line::from(""));
+        contents.push(Line::from("Judge errors".bold()));
+        contents.extend(vler.lines().map(|l| Line::from(l.to_string().red())));
+    }
+
+    contents.push(Line::from(""));
+    contents.push(Line::from("Code".bold()));
+    contents.extend(
+        submission
+            .code
+            .unwrap_or_default()
+            .lines()
+            .map(|l| Line::from(l.to_string())),
+    );
+
+    contents.push(Line::from(""));
+    contents.push(Line::from("Output".bold()));
+    contents.extend(
+        submission
+            .out
+            .unwrap_or_default()
+            .lines()
+            .map(|l| Line::from(l.to_string())),
+    );
+
+    scrolled_paragraph(
+        frame,
+        layout[1],
+        contents,
+        &mut app_state.submission_scroll,
+        Block::bordered().title(format!("#{}", count - selected)),
+    );
+}
+
+fn verdict_label(ok: Option<bool>, verdict: &'static str) -> Span<'static> {
+    match ok {
+        Some(true) => verdict.green(),
+        Some(false) => verdict.red(),
+        None => verdict.yellow(),
+    }
+}
+
+fn scoreboard(
+    app_state: &mut AppState,
     frame: &mut Frame,
+    scoreboard_area: ratatui::prelude::Rect,
 ) {
+    let block = Block::bordered().title("Scoreboard");
+
+    if app_state.scoreboard.is_empty() {
+        frame.render_widget(
+            Paragraph::new("Waiting for scoreboard...".italic()).block(block),
+            scoreboard_area,
+        );
+        return;
+    }
+
+    // Prefer the problem list order, but fall back to whatever the scoreboard reports if the
+    // problems haven't been fetched yet.
+    let columns: Vec<(String, String)> = if app_state.problems.is_empty() {
+        app_state.scoreboard[0]
+            .problems
+            .iter()
+            .map(|p| (p.slug.clone(), p.slug.chars().take(3).collect()))
+            .collect()
+    } else {
+        app_state
+            .problems
+            .iter()
+            .map(|p| (p.slug.clone(), p.icon.clone()))
+            .collect()
+    };
+
+    // Borders and header row.
+    app_state
+        .scoreboard_scroll
+        .set_view_port_height(scoreboard_area.height.saturating_sub(3) as usize);
+    let row_count = app_state.scoreboard.len();
+    app_state.scoreboard_scroll.set_content_length(row_count);
+
+    let own_team = app_state.session.team.as_ref().map(|t| t.id.clone());
+
+    let mut header = vec![
+        Cell::from("#"),
+        Cell::from("Team"),
+        Cell::from("Points"),
+        Cell::from("Penalty"),
+    ];
+    header.extend(columns.iter().map(|(_, label)| Cell::from(label.clone())));
+
+    let rows = app_state
+        .scoreboard
+        .iter()
+        .skip(app_state.scoreboard_scroll.scroll)
+        .map(|row| {
+            let mut cells = vec![
+                Cell::from(row.rank.to_string()),
+                Cell::from(row.team_name.clone()),
+                Cell::from(row.points.to_string()),
+                Cell::from(format!("{:.0}", row.penalty)),
+            ];
+            cells.extend(
+                columns
+                    .iter()
+                    .map(|(slug, _)| problem_score_cell(row.problem(slug))),
+            );
+
+            let row_widget = Row::new(cells);
+            if own_team.as_deref() == Some(row.team_id.as_str()) {
+                row_widget.style(SELECTED_STYLE)
+            } else {
+                row_widget
+            }
+        });
+
+    let mut widths = vec![
+        Constraint::Length(4),
+        Constraint::Min(12),
+        Constraint::Length(7),
+        Constraint::Length(8),
+    ];
+    widths.extend(columns.iter().map(|_| Constraint::Length(4)));
+
+    let table = Table::new(rows, widths)
+        .header(Row::new(header).bold())
+        .block(block);
+
+    frame.render_widget(table, scoreboard_area);
+
+    frame.render_stateful_widget(
+        Scrollbar::new(ScrollbarOrientation::VerticalRight).symbols(scrollbar::VERTICAL),
+        scoreboard_area.inner(Margin {
+            vertical: 1,
+            horizontal: 0,
+        }),
+        &mut app_state.scoreboard_scroll.scroll_state,
+    );
+}
+
+/// Solved problems show the number of tries it took, unsolved ones the tries so far.
+fn problem_score_cell(score: Option<&ProblemScore>) -> Cell<'static> {
+    match score {
+        Some(score) if score.solved => Cell::from(format!("✓{}", score.tries).green()),
+        Some(score) if score.tries > 0 => Cell::from(format!("✗{}", score.tries).red()),
+        _ => Cell::from("·".dark_gray()),
+    }
+}
+
+fn announcements(app_state: &mut AppState, frame: &mut Frame, announcements_area: Rect) {
+    let block = Block::bordered().title("Announcements");
+
+    if app_state.broadcasts.is_empty() {
+        frame.render_widget(
+            Paragraph::new("No announcements yet.".italic()).block(block),
+            announcements_area,
+        );
+        return;
+    }
+
+    // Newest first.
+    let broadcasts: Vec<(String, markdown::mdast::Node)> = app_state
+        .broadcasts
+        .iter()
+        .rev()
+        .map(|b| {
+            let md = markdown::to_mdast(&b.content, &markdown::ParseOptions::default()).unwrap();
+            (b.title.clone(), md)
+        })
+        .collect();
+
+    let mut contents: Vec<Line> = vec![];
+
+    for (title, md) in &broadcasts {
+        contents.push(Line::from(title.clone().bold()));
+        contents.push(Line::from("-".repeat(title.len() + 5)));
+
+        let mut body = vec![];
+        md::render(md, &mut body);
+        contents.extend(body);
+        contents.push(Line::from(""));
+    }
+
+    scrolled_paragraph(
+        frame,
+        announcements_area,
+        contents,
+        &mut app_state.broadcasts_scroll,
+        block,
+    );
+}
+
+/// Renders lines as a wrapped, scrollable paragraph.
+fn scrolled_paragraph(
+    frame: &mut Frame,
+    area: Rect,
+    contents: Vec<Line>,
+    scroll: &mut Scroll,
+    block: Block,
+) {
+    // HACK: Because of line-wrapping in the ratatui paragraph, we need to approximate
+    //       the number of lines ourself.
+    let paragraph_width = area.width.saturating_sub(4) as usize;
+    let lines = contents
+        .iter()
+        .map(|l| number_of_lines_when_broken(&l.to_string(), paragraph_width))
+        .sum::<usize>();
+
+    scroll.set_content_length(lines);
+    scroll.set_view_port_height(area.height.saturating_sub(2) as usize);
+
+    frame.render_widget(
+        Paragraph::new(contents)
+            .wrap(Wrap { trim: false })
+            .block(block)
+            .scroll((scroll.scroll as u16, 0)),
+        area,
+    );
+
+    frame.render_stateful_widget(
+        Scrollbar::new(ScrollbarOrientation::VerticalRight).symbols(scrollbar::VERTICAL),
+        area.inner(Margin {
+            vertical: 1,
+            horizontal: 0,
+        }),
+        &mut scroll.scroll_state,
+    );
+}
+
+fn toast(app_state: &mut AppState, frame: &mut Frame) {
+    if app_state.toast.as_ref().is_some_and(|t| t.is_expired()) {
+        app_state.toast = None;
+    }
+
+    let Some(toast) = &app_state.toast else {
+        return;
+    };
+
+    let area = frame.size();
+    let width = (toast.message.chars().count() as u16 + 4).min(area.width);
+    let toast_area = Rect::new(
+        area.width.saturating_sub(width + 1),
+        1,
+        width,
+        3.min(area.height),
+    );
+
+    frame.render_widget(Clear, toast_area);
+    frame.render_widget(
+        Paragraph::new(toast.message.clone().bold())
+            .block(Block::bordered().border_style(Style::new().yellow())),
+        toast_area,
+    );
+}
+
+fn console(app_state: &mut AppState, console_area: ratatui::prelude::Rect, frame: &mut Frame) {
     app_state
         .console
         .scroll
         .set_view_port_height(console_area.height.saturating_sub(2) as usize);
 
     let width = console_area.width.saturating_sub(2) as usize;
+    app_state.console.prompt_width = prompt(app_state).chars().count();
     app_state.console.set_console_width(width);
 
     let mut console_text: Vec<Line> = app_state
         .console
         .messages
         .iter()
-        .map(|s| {
-            let mut lines = s.lines();
+        .map(|message| {
+            let style = match message.kind {
+                MessageKind::Output => Style::default(),
+                MessageKind::Error => Style::default().red(),
+                MessageKind::Stderr => Style::default().light_red(),
+            };
+            let mut lines = message.text.lines();
 
             if lines.clone().count() == 0 {
                 return vec![];
             }
 
-            let mut text = vec![Line::from(vec!["> ".blue(), lines.next().unwrap().into()])];
+            let first = Span::styled(lines.next().unwrap(), style);
+            let mut text = vec![Line::from(vec!["> ".blue(), first])];
 
             for line in lines {
-                text.push(Line::from(vec!["  ".into(), line.into()]));
+                text.push(Line::from(vec!["  ".into(), Span::styled(line, style)]));
             }
 
             text
@@ -239,14 +682,36 @@ fn console(
         .collect::<Vec<Vec<Line>>>()
         .concat();
 
-    let mut console_input: Vec<Span> = vec!["> ".blue()];
-    if app_state.console.typing {
-        console_input.push(app_state.console.command_buffer.clone().into());
-        console_input.push("█".slow_blink());
+    if let Some(confirm) = &app_state.console.confirm {
+        console_text.push(Line::from(vec![
+            "> ".blue(),
+            confirm.prompt.clone().yellow(),
+            " [y/N] ".into(),
+            "█".slow_blink(),
+        ]));
+    } else if let Some(search) = &app_state.console.search {
+        let history = app_state.console.command_history.entries();
+        let found = search.matched.map_or("", |i| &history[i]);
+        let label = if search.matched.is_some() || search.query.is_empty() {
+            "(reverse-i-search)"
+        } else {
+            "(failed reverse-i-search)"
+        };
+        console_text.push(Line::from(vec![
+            "> ".blue(),
+            format!("{}`{}': ", label, search.query).yellow(),
+            found.replace('\n', " ↵ ").into(),
+            "█".slow_blink(),
+        ]));
+    } else if app_state.console.typing {
+        console_text.extend(input_lines(
+            &app_state.console.command_buffer,
+            prompt(app_state),
+        ));
+    } else {
+        console_text.push(Line::from(prompt(app_state).blue()));
     }
 
-    console_text.push(Line::from(console_input));
-
     frame.render_widget(
         Paragraph::new(console_text)
             .wrap(Wrap { trim: false })
@@ -265,6 +730,153 @@ fn console(
     );
 }
 
+/// Shown before the command being typed: the working directory and the selected problem.
+fn prompt(app_state: &AppState) -> String {
+    let cwd = shell::display_path(app_state.directories.lock().unwrap().current());
+    match app_state.selected_problem() {
+        Some(problem) => format!("{} [{}]> ", cwd, problem.slug),
+        None => format!("{}> ", cwd),
+    }
+}
+
+/// The command being typed, with the character under the cursor highlighted. Later lines get a
+/// `>` lined up with the end of the prompt.
+fn input_lines(editor: &LineEditor, prompt: String) -> Vec<Line<'_>> {
+    let continuation = format!("{:>1$}", "> ", prompt.chars().count());
+    let mut start = 0;
+
+    editor
+        .as_str()
+        .split('\n')
+        .enumerate()
+        .map(|(i, text)| {
+            let end = start + text.len();
+            let mut spans = vec![if i == 0 {
+                prompt.clone().blue()
+            } else {
+                continuation.clone().blue()
+            }];
+
+            if (start..=end).contains(&editor.cursor()) {
+                let (before, after) = text.split_at(editor.cursor() - start);
+                spans.push(before.into());
+                match after.chars().next() {
+                    Some(c) => {
+                        spans.push(after[..c.len_utf8()].reversed());
+                        spans.push(after[c.len_utf8()..].into());
+                    }
+                    None => spans.push("█".slow_blink()),
+                }
+            } else {
+                spans.push(text.into());
+            }
+
+            start = end + 1;
+            Line::from(spans)
+        })
+        .collect()
+}
+
+fn watch_pane(app_state: &AppState, frame: &mut Frame, watch_area: Rect) {
+    let Some(run) = &app_state.watch else {
+        return;
+    };
+
+    let mut text = vec![match &run.result {
+        None => Line::from("Running...".yellow()),
+        Some(result @ RunResult::Finished { status: 0, .. }) => {
+            Line::from(format!("✓ {}", result.summary()).green())
+        }
+        Some(result) => Line::from(format!("✗ {}", result.summary()).red()),
+    }];
+
+    if let Some(submission) = &run.submission {
+        text.push(Line::from(submission.clone().bold()));
+    }
+
+    if let Some(RunResult::Finished { output, .. }) = &run.result {
+        text.push(Line::from(""));
+        // Only the start of the output fits, which is usually enough to spot a mistake.
+        let height = watch_area.height.saturating_sub(2) as usize;
+        text.extend(
+            output
+                .lines()
+                .take(height.saturating_sub(text.len()))
+                .map(|line| Line::from(line.to_string())),
+        );
+    }
+
+    frame.render_widget(
+        Paragraph::new(text)
+            .block(Block::bordered().title(format!("Watching {} for {}", run.file, run.slug))),
+        watch_area,
+    );
+}
+
+/// Most completion candidates shown at once.
+const MAX_COMPLETIONS: usize = 10;
+
+/// Candidates for Tab completion, just above the word being completed.
+fn completion_popup(app_state: &AppState, frame: &mut Frame, console_area: Rect) {
+    let Some(completion) = &app_state.console.completion else {
+        return;
+    };
+
+    let width = completion
+        .candidates
+        .iter()
+        .map(|c| c.chars().count())
+        .max()
+        .unwrap_or(0) as u16
+        + 4;
+    let height = completion.candidates.len().min(MAX_COMPLETIONS) as u16 + 2;
+
+    // Line up with the word being completed, after the border and prompt.
+    let before = &app_state.console.command_buffer.as_str()[..completion.start];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let typed = before[line_start..].chars().count() as u16;
+    let frame_area = frame.size();
+    let prompt = prompt(app_state).chars().count() as u16;
+    let x = (console_area.x + 1 + prompt + typed).min(frame_area.width.saturating_sub(width));
+    // The row the word is shown on, within the console's border.
+    let console = &app_state.console;
+    let row = console.input_row(completion.start) as i64 - console.scroll.scroll as i64;
+    let row = row.clamp(0, console_area.height.saturating_sub(3) as i64) as u16;
+    let input_y = console_area.y + 1 + row;
+    // Below the word instead if there isn't room above it.
+    let y = if input_y >= height {
+        input_y - height
+    } else {
+        input_y + 1
+    };
+    let area = Rect::new(x, y, width, height).intersection(frame_area);
+
+    let list = List::new(completion.candidates.iter().map(|c| c.as_str()))
+        .highlight_style(SELECTED_STYLE)
+        .block(Block::bordered());
+    let mut state = ListState::default().with_selected(completion.selected);
+
+    frame.render_widget(Clear, area);
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+fn connection_label(status: ConnectionStatus) -> Span<'static> {
+    match status {
+        ConnectionStatus::Connecting => "◌ Connecting".yellow(),
+        ConnectionStatus::Connected => "● Connected".green(),
+        ConnectionStatus::Reconnecting { attempt } => {
+            format!("◌ Reconnecting ({})", attempt).yellow()
+        }
+        ConnectionStatus::Offline { retry_at } => format!(
+            "○ Offline, retrying in {}s",
+            retry_at
+                .saturating_duration_since(std::time::Instant::now())
+                .as_secs()
+        )
+        .red(),
+    }
+}
+
 fn difficulty_label(difficulty: i64) -> Span<'static> {
     match difficulty {
         1 => "Easy".green(),
@@ -0,0 +1,57 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+/// The main view shown above the console. Cycled with `Tab`/`BackTab`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    #[default]
+    Competition,
+    Problems,
+    Submissions,
+    Scoreboard,
+    Announcements,
+}
+
+impl View {
+    pub const ALL: [View; 5] = [
+        View::Competition,
+        View::Problems,
+        View::Submissions,
+        View::Scoreboard,
+        View::Announcements,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            View::Competition => "Competition",
+            View::Problems => "Problems",
+            View::Submissions => "Submissions",
+            View::Scoreboard => "Scoreboard",
+            View::Announcements => "Announcements",
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        Self::ALL.iter().position(|v| v == self).unwrap_or(0)
+    }
+
+    pub fn next(&self) -> Self {
+        Self::ALL[(self.index() + 1) % Self::ALL.len()]
+    }
+
+    pub fn previous(&self) -> Self {
+        Self::ALL[(self.index() + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+}
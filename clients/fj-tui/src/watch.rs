@@ -0,0 +1,57 @@
+/*
+ * This program is free software: you can redistribute it and/or modify it
+ * under the terms of the GNU Lesser General Public License as published by the
+ * Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of MERCHANTABILITY
+ * or FITNESS FOR A PARTICULAR PURPOSE. See the GNU Lesser General Public License
+ * for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public License along
+ * with this program. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::time::Duration;
+
+/// How a run of a solution went.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunResult {
+    Finished {
+        status: i32,
+        elapsed: Duration,
+        output: String,
+    },
+    /// Stopped at the time limit.
+    TimedOut(Duration),
+}
+
+impl RunResult {
+    pub fn summary(&self) -> String {
+        match self {
+            RunResult::Finished {
+                status, elapsed, ..
+            } => format!(
+                "Exited with status {} in {:.2}s",
+                status,
+                elapsed.as_secs_f64()
+            ),
+            RunResult::TimedOut(limit) => {
+                format!("Stopped after the {}s time limit", limit.as_secs_f64())
+            }
+        }
+    }
+}
+
+/// The latest run of a watched solution, shown in the watch pane.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchRun {
+    /// File whose change started the run.
+    pub file: String,
+    pub slug: String,
+    /// `None` while the solution is still running.
+    pub result: Option<RunResult>,
+    /// What happened when submitting automatically.
+    pub submission: Option<String>,
+}